@@ -7,17 +7,114 @@
 //! ## Key Functions
 //! - `mine_create2_accounts`: Main entry point for mining CREATE2 contracts with auxiliary accounts
 //! - `calculate_create2_address`: Computes deterministic CREATE2 addresses
-//! - `mine_auxiliaries_for_contract`: Mines accounts whose hashes share prefixes with a contract
+//! - `mine_create_accounts`: Mines CREATE (nonce-based) contracts with auxiliary accounts
+//! - `calculate_create_address`: Computes CREATE addresses from sender and nonce
+//! - `SaltRange`: The 32-byte salts a run mines, built from a prefix and a counter
+//! - `submit_auxiliaries`: Queues accounts whose hashes share prefixes with a contract
+//! - `mine_auxiliaries_batch`: Mines the auxiliaries of many contracts against one target index
+//! - `submit_deployer_search`: Queues a search for a deployer key whose first contract shares a prefix
+//! - `mine_salt_for_prefix`: Searches salts for a contract whose hash shares a prefix with a target
+//! - `mine_account_branch`: Mines a chain of accounts, optionally below an existing state path
+//! - `load_checkpoint`: Validates a checkpoint against the run parameters before resuming
 
+use crate::checkpoint;
+use crate::engine::{JobHandle, MiningEngine};
+use crate::factories::SaltGuard;
+use crate::keys;
+use crate::layout;
+use crate::mpt::{self, Trie, TrieReport};
+use crate::rlp;
+use crate::search::{self, Prefix, count_shared_nibbles, has_nibble_prefix, prefix_matches};
+use crate::snapshot::{SnapshotAnchor, SnapshotInfo};
+use crate::targets::TargetIndex;
 use log::{debug, info};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
+use secp256k1::{Secp256k1, SecretKey};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fs;
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::Arc;
 use std::time::Instant;
 use tiny_keccak::{Hasher, Keccak};
 
+/// A contract address together with its mined auxiliary accounts
+pub type MinedContract = ([u8; 20], Vec<[u8; 20]>);
+
+/// A contract address with its auxiliaries and their private keys (empty unless mined as EOAs)
+type MinedAuxiliaries = ([u8; 20], Vec<[u8; 20]>, Vec<SecretKey>);
+
+/// Engine priority of salt and deployer searches, which every later contract waits for
+const CONTRACT_PRIORITY: i64 = 1;
+/// Engine priority of auxiliary searches, which run alongside those of the next contract
+const AUXILIARY_PRIORITY: i64 = 0;
+
+/// The salts of a CREATE2 run: contract `i` uses `prefix` with `start + i` added to its low
+/// bytes, so runs with disjoint counter ranges mine disjoint contract sets
+///
+/// These are the salts the deployer is called with. A factory that binds them to a caller
+/// (CreateX) passes CREATE2 the salt its `SaltGuard` derives instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaltRange {
+    prefix: Vec<u8>,
+    start: u64,
+}
+
+impl SaltRange {
+    /// Salts for `count` contracts below a prefix of at most 32 bytes; the counter must fit
+    /// into the bytes the prefix leaves free
+    pub fn new(prefix: Vec<u8>, start: u64, count: u64) -> Result<Self, String> {
+        if prefix.len() > 32 {
+            return Err(format!(
+                "Salt prefix is {} bytes, at most 32 are allowed",
+                prefix.len()
+            ));
+        }
+
+        let last = start
+            .checked_add(count.saturating_sub(1))
+            .ok_or("Salt range exceeds 64 bits")?;
+        let free_bits = (32 - prefix.len()) * 8;
+        if free_bits < 64 && last >> free_bits != 0 {
+            return Err(format!(
+                "Salt counter {last} does not fit into the {} bytes after the prefix",
+                32 - prefix.len()
+            ));
+        }
+
+        Ok(SaltRange { prefix, start })
+    }
+
+    /// Salts for a salt search, which may use any counter from `start` on, so the prefix has
+    /// to leave the full 8 counter bytes free
+    pub fn unbounded(prefix: Vec<u8>, start: u64) -> Result<Self, String> {
+        if prefix.len() > 24 {
+            return Err(format!(
+                "Salt prefix is {} bytes, searching salts needs at most 24",
+                prefix.len()
+            ));
+        }
+        Ok(SaltRange { prefix, start })
+    }
+
+    /// Salt holding an arbitrary counter value below the prefix
+    pub fn salt_for_counter(&self, counter: u64) -> [u8; 32] {
+        let mut base = [0u8; 32];
+        base[..self.prefix.len()].copy_from_slice(&self.prefix);
+        layout::add_u64(&base, counter)
+    }
+
+    /// Counter of a salt below this prefix, or `None` if the salt does not belong to the range
+    fn counter_of(&self, salt: &[u8; 32]) -> Option<u64> {
+        // A prefix longer than 24 bytes also fills the low word the counter is added to
+        let low_word = |word: &[u8; 32]| u64::from_be_bytes(word[24..].try_into().unwrap());
+        let counter = low_word(salt).wrapping_sub(low_word(&self.salt_for_counter(0)));
+        (counter >= self.start && self.salt_for_counter(counter) == *salt).then_some(counter)
+    }
+
+    fn prefix_hex(&self) -> String {
+        format!("0x{}", hex::encode(&self.prefix))
+    }
+}
+
 /// Result structure for CREATE2-based mining
 #[derive(Serialize, Deserialize)]
 pub struct Create2MiningResult {
@@ -25,6 +122,28 @@ pub struct Create2MiningResult {
     pub init_code_hash: String,
     pub target_depth: usize,
     pub num_contracts: usize,
+    /// Bytes every salt starts with (`0x` when the whole salt is the counter)
+    #[serde(default = "empty_prefix")]
+    pub salt_prefix: String,
+    /// Counter value of the first contract's salt
+    #[serde(default)]
+    pub salt_start: u64,
+    /// Account calling CreateX, which binds salts starting with it to that caller
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    /// Chain id CreateX binds cross-chain protected salts to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    /// Nibbles every contract's hashed address shares with `contract_target`, found by
+    /// searching salts (0 when salts are used in order)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub contract_nibbles: usize,
+    /// Hashed account key the contracts were mined towards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_target: Option<String>,
+    /// Whether every auxiliary shares exactly its depth in nibbles with the contract hash
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
     pub total_time: f64,
     pub contracts: Vec<ContractWithAuxiliaries>,
 }
@@ -32,19 +151,180 @@ pub struct Create2MiningResult {
 /// Contract with its auxiliary accounts
 #[derive(Serialize, Deserialize)]
 pub struct ContractWithAuxiliaries {
-    pub salt: u32,
+    /// Full 32-byte salt as hex
+    #[serde(deserialize_with = "deserialize_salt")]
+    pub salt: String,
+    pub contract_address: String,
+    pub auxiliary_accounts: Vec<String>,
+    /// Private keys of the auxiliary accounts, in the same order, when they were mined as EOAs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auxiliary_keys: Vec<String>,
+    /// Nibbles each auxiliary's hash actually shares with the contract hash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auxiliary_shared_nibbles: Vec<usize>,
+    /// Path to the contract in an account trie built from all mined accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trie: Option<TrieReport>,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn empty_prefix() -> String {
+    "0x".to_string()
+}
+
+/// Results written before 32-byte salts stored the salt as a plain counter; read those as
+/// the equivalent left-padded salt
+fn deserialize_salt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Salt {
+        Counter(u64),
+        Hex(String),
+    }
+
+    Ok(match Salt::deserialize(deserializer)? {
+        Salt::Counter(counter) => format!("0x{}", hex::encode(layout::add_u64(&[0; 32], counter))),
+        Salt::Hex(salt) => salt,
+    })
+}
+
+impl Create2MiningResult {
+    /// How the deployer turns the recorded salts into CREATE2 salts
+    pub fn salt_guard(&self) -> Result<SaltGuard, String> {
+        let caller = self
+            .caller
+            .as_deref()
+            .map(crate::parse_hex_array::<20>)
+            .transpose()?;
+        Ok(SaltGuard::for_deployer(
+            &crate::parse_hex_array(&self.deployer)?,
+            caller,
+            self.chain_id,
+        ))
+    }
+
+    /// Whether the auxiliary accounts were mined as EOAs with private keys
+    pub fn has_auxiliary_keys(&self) -> bool {
+        self.contracts
+            .iter()
+            .any(|contract| !contract.auxiliary_keys.is_empty())
+    }
+}
+
+/// Where CREATE contracts come from
+pub enum CreateDeployers {
+    /// Consecutive nonces of an existing deployer account
+    Nonces {
+        deployer: [u8; 20],
+        first_nonce: u64,
+    },
+    /// One mined deployer key per contract, whose first deployment (nonce 0) hashes to
+    /// `nibbles` shared nibbles with `target` (or with the first contract)
+    Mined {
+        nibbles: usize,
+        target: Option<[u8; 32]>,
+    },
+}
+
+/// Result structure for CREATE-based mining
+#[derive(Serialize, Deserialize)]
+pub struct CreateMiningResult {
+    pub target_depth: usize,
+    pub num_contracts: usize,
+    /// Nibbles every contract's hashed address shares with `contract_target` (0 for the
+    /// nonce range of a given deployer)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub contract_nibbles: usize,
+    /// Hashed account key the deployers were mined towards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_target: Option<String>,
+    /// Whether every auxiliary shares exactly its depth in nibbles with the contract hash
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+    pub total_time: f64,
+    pub contracts: Vec<CreatedContract>,
+}
+
+/// Contract deployed with CREATE, with its auxiliary accounts
+#[derive(Serialize, Deserialize)]
+pub struct CreatedContract {
+    pub deployer: String,
+    /// Private key of the deployer, when it was mined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployer_key: Option<String>,
+    pub nonce: u64,
     pub contract_address: String,
     pub auxiliary_accounts: Vec<String>,
+    /// Private keys of the auxiliary accounts, in the same order, when they were mined as EOAs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auxiliary_keys: Vec<String>,
+    /// Nibbles each auxiliary's hash actually shares with the contract hash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auxiliary_shared_nibbles: Vec<usize>,
+    /// Path to the contract in an account trie built from all mined accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trie: Option<TrieReport>,
+}
+
+/// Result structure for mining a chain of accounts deep in the account trie
+#[derive(Serialize, Deserialize)]
+pub struct AccountBranchResult {
+    pub depth: usize,
+    /// Whether every level shares exactly its required nibbles with the previous one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+    pub total_time: f64,
+    pub accounts: Vec<MinedAccount>,
+    /// Path to the deepest account in a trie built from the mined accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trie: Option<TrieReport>,
+    /// Existing state path the branch was mined below, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotInfo>,
+}
+
+/// A single mined account of the branch
+#[derive(Serialize, Deserialize)]
+pub struct MinedAccount {
+    pub address: String,
+    pub hashed_key: String,
+    /// Nibbles the hashed key shares with the previous level (the snapshot anchor for the
+    /// first level, or 0 without one)
+    pub shared_nibbles: usize,
 }
 
 /// Main entry point for CREATE2-based account mining
+///
+/// With `contract_nibbles > 0` the salts are searched instead of used in order: every contract's
+/// hashed address then shares that many nibbles with `contract_target` (or, without one, with
+/// the first contract), and auxiliary accounts are only mined for the levels below. With
+/// `eoa_keys` the auxiliaries are mined as private keys, which end up in the JSON output, and
+/// with `exact` each auxiliary shares exactly its depth in nibbles with the contract hash.
+/// With `batch` all contracts are found first and their auxiliaries are then mined together
+/// (see `mine_auxiliaries_batch`).
+///
+/// Progress is checkpointed next to `output_path` as contracts finish, at most once per
+/// `checkpoint::INTERVAL`. A `resumed` result (see `load_checkpoint`) keeps its contracts and
+/// mining continues with the next salt.
+#[allow(clippy::too_many_arguments)]
 pub fn mine_create2_accounts(
     deployer: [u8; 20],
+    salts: &SaltRange,
+    guard: &SaltGuard,
+    contract_nibbles: usize,
+    contract_target: Option<[u8; 32]>,
+    eoa_keys: bool,
+    exact: bool,
+    batch: bool,
     num_contracts: usize,
     target_depth: usize,
     num_threads: usize,
     init_code: &[u8],
     output_path: &str,
+    resumed: Option<Create2MiningResult>,
 ) {
     info!("");
     info!("╔════════════════════════════════════════════════════════════════════════╗");
@@ -53,8 +333,35 @@ pub fn mine_create2_accounts(
     info!("");
     info!("Deployer: 0x{}", hex::encode(deployer));
     info!("Contracts to deploy: {num_contracts}");
+    info!(
+        "Salts: prefix {}, counter {}..{}",
+        salts.prefix_hex(),
+        salts.start,
+        salts.start + num_contracts as u64
+    );
+    if *guard != SaltGuard::Plain {
+        info!("Salts are guarded by CreateX before CREATE2");
+    }
+    if contract_nibbles > 0 {
+        info!(
+            "Searching salts for contracts sharing {contract_nibbles} nibbles with {}",
+            contract_target.map_or("the first contract".to_string(), |target| format!(
+                "0x{}",
+                hex::encode(target)
+            ))
+        );
+    }
     info!("Target trie depth: {target_depth}");
     info!("Mining threads: {num_threads}");
+    if eoa_keys {
+        info!("Auxiliary accounts: EOAs with private keys");
+    }
+    if exact {
+        info!("Auxiliary accounts share exactly their depth in nibbles");
+    }
+    if batch {
+        info!("Auxiliary accounts: mined in one batch for all contracts");
+    }
     info!("");
 
     let total_start = Instant::now();
@@ -63,14 +370,95 @@ pub fn mine_create2_accounts(
     let init_code_hash = keccak256(init_code);
     info!("Init code hash: 0x{}", hex::encode(init_code_hash));
 
-    let mut contracts = Vec::new();
+    let mut result = resumed.unwrap_or_else(|| Create2MiningResult {
+        deployer: format!("0x{}", hex::encode(deployer)),
+        init_code_hash: format!("0x{}", hex::encode(init_code_hash)),
+        target_depth,
+        num_contracts,
+        salt_prefix: salts.prefix_hex(),
+        salt_start: salts.start,
+        caller: guard
+            .caller()
+            .map(|caller| format!("0x{}", hex::encode(caller))),
+        chain_id: guard.chain_id(),
+        contract_nibbles,
+        contract_target: contract_target.map(|target| format!("0x{}", hex::encode(target))),
+        exact,
+        total_time: 0.0,
+        contracts: Vec::new(),
+    });
+    result.num_contracts = num_contracts;
+    let previous_time = result.total_time;
 
-    // Process each contract
-    for contract_idx in 0..num_contracts {
-        let salt = contract_idx as u32;
+    let mut target = result
+        .contract_target
+        .as_deref()
+        .map(crate::parse_hex_array::<32>)
+        .transpose()
+        .expect("resumed target is validated by load_checkpoint");
+    let mut mined_accounts = verify_contracts(
+        &deployer,
+        guard,
+        &init_code_hash,
+        target_depth,
+        contract_nibbles,
+        target.as_ref(),
+        exact,
+        &result.contracts,
+    )
+    .expect("resumed contracts are validated by load_checkpoint");
+
+    // Salts are taken in counter order, so mining continues after the last one used
+    let mut next_counter = match result.contracts.last() {
+        Some(contract) => {
+            let salt = crate::parse_hex_array(&contract.salt).expect("validated salt");
+            salts.counter_of(&salt).expect("validated salt") + 1
+        }
+        None => salts.start,
+    };
+    if !result.contracts.is_empty() {
+        info!(
+            "Resuming with {} of {num_contracts} contracts already mined",
+            result.contracts.len()
+        );
+    }
+
+    let mut checkpoint =
+        checkpoint::Periodic::new(checkpoint::path_for(output_path), checkpoint::INTERVAL);
+    let engine = MiningEngine::shared(num_threads);
+
+    // Process each contract. The auxiliaries of one contract are still being mined while the
+    // next contract's salt is searched and its own auxiliaries start, unless they are left for
+    // a batch over all contracts.
+    let mut pending: Option<(ContractWithAuxiliaries, PendingAuxiliaries)> = None;
+    let mut planned = Vec::new();
+    for contract_idx in result.contracts.len()..num_contracts {
+        let counter = match target {
+            Some(target) if contract_nibbles > 0 => mine_salt_for_prefix(
+                &engine,
+                &deployer,
+                salts,
+                guard,
+                next_counter,
+                &init_code_hash,
+                &target,
+                contract_nibbles,
+            ),
+            _ => next_counter,
+        };
+        next_counter = counter + 1;
+        let salt = salts.salt_for_counter(counter);
 
-        // Calculate CREATE2 address
-        let contract_address = calculate_create2_address(&deployer, salt, &init_code_hash);
+        // Calculate CREATE2 address from the salt the deployer passes on
+        let create2_salt = guard
+            .apply(&salt)
+            .expect("salt range checked against the guard");
+        let contract_address = calculate_create2_address(&deployer, &create2_salt, &init_code_hash);
+        if contract_nibbles > 0 && target.is_none() {
+            // Without an explicit target the later contracts gather around the first one
+            target = Some(keccak256(&contract_address));
+            result.contract_target = target.map(|target| format!("0x{}", hex::encode(target)));
+        }
 
         info!(
             "Contract {}/{} - Address: 0x{}...",
@@ -79,50 +467,441 @@ pub fn mine_create2_accounts(
             hex::encode(&contract_address[..4])
         );
 
-        // Mine auxiliary accounts for this contract
-        let auxiliaries =
-            mine_auxiliaries_for_contract(&contract_address, target_depth, num_threads);
+        let contract = ContractWithAuxiliaries {
+            salt: format!("0x{}", hex::encode(salt)),
+            contract_address: format!("0x{}", hex::encode(contract_address)),
+            auxiliary_accounts: Vec::new(),
+            auxiliary_keys: Vec::new(),
+            auxiliary_shared_nibbles: Vec::new(),
+            trie: None,
+        };
+        if batch {
+            planned.push((contract, contract_address));
+            continue;
+        }
 
-        contracts.push(ContractWithAuxiliaries {
-            salt,
+        // Mine auxiliary accounts for the levels the contracts do not already share
+        let auxiliaries = submit_auxiliaries(
+            &engine,
+            &contract_address,
+            contract_nibbles + 1,
+            target_depth,
+            eoa_keys,
+            exact,
+        );
+        if let Some((contract, auxiliaries)) = pending.replace((contract, auxiliaries)) {
+            finish_create2_contract(
+                &mut result,
+                &mut mined_accounts,
+                contract,
+                auxiliaries.wait(),
+                previous_time,
+                total_start,
+                &mut checkpoint,
+            );
+        }
+    }
+    if let Some((contract, auxiliaries)) = pending {
+        finish_create2_contract(
+            &mut result,
+            &mut mined_accounts,
+            contract,
+            auxiliaries.wait(),
+            previous_time,
+            total_start,
+            &mut checkpoint,
+        );
+    }
+
+    if !planned.is_empty() {
+        // Contracts complete in any order, but are recorded (and checkpointed) in salt order
+        let addresses: Vec<[u8; 20]> = planned.iter().map(|(_, address)| *address).collect();
+        let mut contracts = planned.into_iter().map(|(contract, _)| contract);
+        let mut completed: Vec<Option<MinedAuxiliaries>> = vec![None; addresses.len()];
+        let mut next = 0;
+        mine_auxiliaries_batch(
+            &engine,
+            &addresses,
+            contract_nibbles + 1,
+            target_depth,
+            eoa_keys,
+            exact,
+            |index, mined| {
+                completed[index] = Some(mined);
+                while let Some(Some(mined)) = completed.get_mut(next).map(Option::take) {
+                    let contract = contracts.next().expect("one contract per address");
+                    finish_create2_contract(
+                        &mut result,
+                        &mut mined_accounts,
+                        contract,
+                        mined,
+                        previous_time,
+                        total_start,
+                        &mut checkpoint,
+                    );
+                    next += 1;
+                }
+            },
+        );
+    }
+
+    // Check the depth each contract actually reaches in the resulting account trie
+    let trie = build_account_trie(&mined_accounts);
+    let reports: Vec<TrieReport> = mined_accounts
+        .iter()
+        .map(|(contract_address, _)| trie.report(&keccak256(contract_address)))
+        .collect();
+    log_trie_depths(&reports);
+    for (contract, report) in result.contracts.iter_mut().zip(reports) {
+        contract.trie = Some(report);
+    }
+
+    let total_time = previous_time + total_start.elapsed().as_secs_f64();
+    result.total_time = total_time;
+
+    // Write to JSON file
+    match serde_json::to_string_pretty(&result) {
+        Ok(json) => {
+            if let Err(e) = fs::write(output_path, json) {
+                log::error!("Failed to write JSON: {e}");
+            } else {
+                info!("");
+                info!("═══ CREATE2 Mining Statistics ═══");
+                info!("Total contracts: {num_contracts}");
+                info!("Target depth: {target_depth}");
+                info!(
+                    "Total auxiliary accounts: {}",
+                    num_contracts * target_depth.saturating_sub(contract_nibbles)
+                );
+                info!("Total time: {total_time:.2} seconds");
+                info!(
+                    "Average time per contract: {:.2} seconds",
+                    total_time / num_contracts as f64
+                );
+                info!("Results saved to: {output_path}");
+                checkpoint::remove(checkpoint.path());
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to serialize to JSON: {e}");
+        }
+    }
+}
+
+/// Record a contract with its mined auxiliaries and checkpoint the progress when due
+fn finish_create2_contract(
+    result: &mut Create2MiningResult,
+    mined_accounts: &mut Vec<MinedContract>,
+    mut contract: ContractWithAuxiliaries,
+    (contract_address, auxiliaries, keys): MinedAuxiliaries,
+    previous_time: f64,
+    total_start: Instant,
+    checkpoint: &mut checkpoint::Periodic,
+) {
+    info!(
+        "  Mined {} auxiliary accounts for 0x{}...",
+        auxiliaries.len(),
+        hex::encode(&contract_address[..4])
+    );
+
+    contract.auxiliary_accounts = auxiliaries
+        .iter()
+        .map(|a| format!("0x{}", hex::encode(a)))
+        .collect();
+    contract.auxiliary_keys = keys.iter().map(keys::format_secret).collect();
+    contract.auxiliary_shared_nibbles = shared_nibbles_with(&contract_address, &auxiliaries);
+    result.contracts.push(contract);
+    mined_accounts.push((contract_address, auxiliaries));
+
+    result.total_time = previous_time + total_start.elapsed().as_secs_f64();
+    if let Err(e) = checkpoint.save_if_due(result) {
+        log::error!("Failed to save checkpoint: {e}");
+    }
+}
+
+/// Main entry point for CREATE-based account mining
+///
+/// Each contract gets auxiliary accounts for the levels below the nibbles it already shares
+/// (all levels for a given deployer's nonce range). With `batch` they are mined together for
+/// all contracts once every contract is known.
+#[allow(clippy::too_many_arguments)]
+pub fn mine_create_accounts(
+    deployers: &CreateDeployers,
+    num_contracts: usize,
+    target_depth: usize,
+    eoa_keys: bool,
+    exact: bool,
+    batch: bool,
+    num_threads: usize,
+    output_path: &str,
+) {
+    info!("");
+    info!("╔════════════════════════════════════════════════════════════════════════╗");
+    info!("║                       CREATE ACCOUNT MINING MODE                       ║");
+    info!("╚════════════════════════════════════════════════════════════════════════╝");
+    info!("");
+
+    let (contract_nibbles, mut target) = match deployers {
+        CreateDeployers::Nonces {
+            deployer,
+            first_nonce,
+        } => {
+            info!("Deployer: 0x{}", hex::encode(deployer));
+            info!(
+                "Nonces: {first_nonce}..{}",
+                first_nonce + num_contracts as u64
+            );
+            (0, None)
+        }
+        CreateDeployers::Mined { nibbles, target } => {
+            info!("Mining one deployer key per contract (deploying at nonce 0)");
+            info!(
+                "Contracts share {nibbles} nibbles with {}",
+                target.map_or("the first contract".to_string(), |target| format!(
+                    "0x{}",
+                    hex::encode(target)
+                ))
+            );
+            (*nibbles, *target)
+        }
+    };
+    info!("Contracts to deploy: {num_contracts}");
+    info!("Target trie depth: {target_depth}");
+    info!("Mining threads: {num_threads}");
+    info!("");
+
+    let total_start = Instant::now();
+    let secp = Secp256k1::signing_only();
+    let engine = MiningEngine::shared(num_threads);
+    let mut pending = Vec::new();
+
+    // Deployer keys are mined one after another, each contract's auxiliaries keep running
+    // in the background until all contracts are collected below
+    for contract_idx in 0..num_contracts {
+        let (deployer, deployer_key, nonce) = match deployers {
+            CreateDeployers::Nonces {
+                deployer,
+                first_nonce,
+            } => (*deployer, None, first_nonce + contract_idx as u64),
+            CreateDeployers::Mined { nibbles, .. } => {
+                let (deployer, key) = match target {
+                    Some(target) => {
+                        submit_deployer_search(&engine, Prefix::new(target, *nibbles, false))
+                            .wait()
+                            .expect("Failed to find deployer key")
+                    }
+                    None => search::random_eoa(secp.clone())(&mut fastrand::Rng::new(), 0),
+                };
+                (deployer, Some(key), 0)
+            }
+        };
+
+        let contract_address = calculate_create_address(&deployer, nonce);
+        if contract_nibbles > 0 && target.is_none() {
+            // Without an explicit target the later contracts gather around the first one
+            target = Some(keccak256(&contract_address));
+        }
+
+        info!(
+            "Contract {}/{} - Address: 0x{}... (nonce {nonce} of 0x{}...)",
+            contract_idx + 1,
+            num_contracts,
+            hex::encode(&contract_address[..4]),
+            hex::encode(&deployer[..4])
+        );
+
+        let auxiliaries = (!batch).then(|| {
+            submit_auxiliaries(
+                &engine,
+                &contract_address,
+                contract_nibbles + 1,
+                target_depth,
+                eoa_keys,
+                exact,
+            )
+        });
+        pending.push((deployer, deployer_key, nonce, contract_address, auxiliaries));
+    }
+
+    let mut batched: Vec<Option<MinedAuxiliaries>> = vec![None; pending.len()];
+    if batch {
+        let addresses: Vec<[u8; 20]> = pending.iter().map(|contract| contract.3).collect();
+        mine_auxiliaries_batch(
+            &engine,
+            &addresses,
+            contract_nibbles + 1,
+            target_depth,
+            eoa_keys,
+            exact,
+            |index, mined| batched[index] = Some(mined),
+        );
+    }
+
+    let mut contracts = Vec::new();
+    let mut mined_accounts: Vec<MinedContract> = Vec::new();
+    for (index, (deployer, deployer_key, nonce, _, auxiliaries)) in pending.into_iter().enumerate()
+    {
+        let (contract_address, auxiliaries, auxiliary_keys) = match auxiliaries {
+            Some(auxiliaries) => auxiliaries.wait(),
+            None => batched[index].take().expect("batch mines every contract"),
+        };
+        info!(
+            "  Mined {} auxiliary accounts for 0x{}...",
+            auxiliaries.len(),
+            hex::encode(&contract_address[..4])
+        );
+
+        contracts.push(CreatedContract {
+            deployer: format!("0x{}", hex::encode(deployer)),
+            deployer_key: deployer_key.as_ref().map(keys::format_secret),
+            nonce,
             contract_address: format!("0x{}", hex::encode(contract_address)),
             auxiliary_accounts: auxiliaries
                 .iter()
                 .map(|a| format!("0x{}", hex::encode(a)))
                 .collect(),
+            auxiliary_keys: auxiliary_keys.iter().map(keys::format_secret).collect(),
+            auxiliary_shared_nibbles: shared_nibbles_with(&contract_address, &auxiliaries),
+            trie: None,
         });
-
-        info!("  Mined {} auxiliary accounts", auxiliaries.len());
+        mined_accounts.push((contract_address, auxiliaries));
     }
 
-    let total_time = total_start.elapsed().as_secs_f64();
+    // Check the depth each contract actually reaches in the resulting account trie
+    let trie = build_account_trie(&mined_accounts);
+    let reports: Vec<TrieReport> = mined_accounts
+        .iter()
+        .map(|(contract_address, _)| trie.report(&keccak256(contract_address)))
+        .collect();
+    log_trie_depths(&reports);
+    for (contract, report) in contracts.iter_mut().zip(reports) {
+        contract.trie = Some(report);
+    }
 
-    // Create result structure
-    let result = Create2MiningResult {
-        deployer: format!("0x{}", hex::encode(deployer)),
-        init_code_hash: format!("0x{}", hex::encode(init_code_hash)),
+    let result = CreateMiningResult {
         target_depth,
         num_contracts,
-        total_time,
+        contract_nibbles,
+        contract_target: target
+            .filter(|_| contract_nibbles > 0)
+            .map(|target| format!("0x{}", hex::encode(target))),
+        exact,
+        total_time: total_start.elapsed().as_secs_f64(),
         contracts,
     };
 
-    // Write to JSON file
     match serde_json::to_string_pretty(&result) {
         Ok(json) => {
             if let Err(e) = fs::write(output_path, json) {
                 log::error!("Failed to write JSON: {e}");
             } else {
-                info!("");
-                info!("═══ CREATE2 Mining Statistics ═══");
-                info!("Total contracts: {num_contracts}");
-                info!("Target depth: {target_depth}");
-                info!("Total auxiliary accounts: {}", num_contracts * target_depth);
-                info!("Total time: {total_time:.2} seconds");
+                info!("Total time: {:.2} seconds", result.total_time);
+                info!("Results saved to: {output_path}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to serialize to JSON: {e}");
+        }
+    }
+}
+
+/// Mine a chain of accounts whose hashed keys share an increasing prefix
+///
+/// Without an anchor the first account is random, like the first storage level. With one,
+/// the first account must share one nibble more than the deepest existing path, so the
+/// whole chain hangs below real state. With `exact` each level shares exactly its required
+/// nibbles, so no two levels collapse into the same branch node.
+pub fn mine_account_branch(
+    target_depth: usize,
+    num_threads: usize,
+    anchor: Option<&SnapshotAnchor>,
+    exact: bool,
+    output_path: &str,
+) {
+    info!("");
+    info!("╔════════════════════════════════════════════════════════════════════════╗");
+    info!("║                      ACCOUNT BRANCH MINING MODE                        ║");
+    info!("╚════════════════════════════════════════════════════════════════════════╝");
+    info!("");
+
+    let total_start = Instant::now();
+    let base_nibbles = anchor.map_or(0, SnapshotAnchor::required_nibbles);
+    if let Some(anchor) = anchor {
+        info!(
+            "Extending existing path at 0x{} ({} shared nibbles among {} snapshot keys)",
+            hex::encode(anchor.key),
+            anchor.shared_nibbles,
+            anchor.num_keys
+        );
+    }
+
+    let mut addresses: Vec<[u8; 20]> = Vec::new();
+    let mut hashes: Vec<[u8; 32]> = Vec::new();
+
+    for level in 0..target_depth {
+        let required = base_nibbles + level;
+        let reference = hashes.last().or(anchor.map(|anchor| &anchor.key));
+
+        let address = match reference {
+            Some(reference) => {
                 info!(
-                    "Average time per contract: {:.2} seconds",
-                    total_time / num_contracts as f64
+                    "Mining account {}/{} (requires {} matching nibbles)",
+                    level + 1,
+                    target_depth,
+                    required
                 );
+                mine_account_with_hash_prefix(reference, required, exact, num_threads)
+            }
+            None => {
+                let mut address = [0u8; 20];
+                rand::thread_rng().fill(&mut address);
+                address
+            }
+        };
+
+        addresses.push(address);
+        hashes.push(keccak256(&address));
+    }
+
+    let mut accounts = Vec::new();
+    for (i, (address, hash)) in addresses.iter().zip(&hashes).enumerate() {
+        let shared_nibbles = match (i, anchor) {
+            (0, None) => 0,
+            (0, Some(anchor)) => count_shared_nibbles(&anchor.key, hash),
+            _ => count_shared_nibbles(&hashes[i - 1], hash),
+        };
+        accounts.push(MinedAccount {
+            address: format!("0x{}", hex::encode(address)),
+            hashed_key: format!("0x{}", hex::encode(hash)),
+            shared_nibbles,
+        });
+    }
+
+    let result = AccountBranchResult {
+        depth: accounts.len(),
+        exact,
+        total_time: total_start.elapsed().as_secs_f64(),
+        trie: verify_account_branch_trie(&hashes, anchor),
+        snapshot: anchor.map(SnapshotAnchor::info),
+        accounts,
+    };
+
+    if let Some(report) = &result.trie {
+        info!(
+            "Verified trie depth: {} nodes ({} branch nodes) to the deepest account",
+            report.depth, report.branch_nodes
+        );
+        if let Some(proof) = &report.proof {
+            info!("Account proof: {}", proof.describe());
+        }
+    }
+
+    match serde_json::to_string_pretty(&result) {
+        Ok(json) => {
+            if let Err(e) = fs::write(output_path, json) {
+                log::error!("Failed to write JSON: {e}");
+            } else {
+                info!("Total time: {:.2} seconds", result.total_time);
                 info!("Results saved to: {output_path}");
             }
         }
@@ -132,10 +911,527 @@ pub fn mine_create2_accounts(
     }
 }
 
+/// Insert the mined account keys (and the snapshot anchor) into a trie and report the path
+/// to the deepest one
+fn verify_account_branch_trie(
+    hashes: &[[u8; 32]],
+    anchor: Option<&SnapshotAnchor>,
+) -> Option<TrieReport> {
+    let deepest = hashes.last()?;
+    let account = mpt::account_rlp(0, 1, &mpt::EMPTY_ROOT, &mpt::EMPTY_CODE_HASH);
+
+    let mut trie = Trie::new();
+    for key in anchor.map(|anchor| &anchor.key).into_iter().chain(hashes) {
+        trie.insert(key, account.clone());
+    }
+    Some(trie.report(deepest))
+}
+
+/// Load an account branch result from a JSON file
+pub fn load_branch_results(path: &str) -> Result<AccountBranchResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid account branch JSON: {e}"))
+}
+
+/// Check that every hashed key matches its address and that each level shares the
+/// required prefix with the previous one (or the snapshot anchor), exactly for exact results
+pub fn verify_branch_results(result: &AccountBranchResult) -> Result<(), String> {
+    let anchor = result
+        .snapshot
+        .as_ref()
+        .map(SnapshotInfo::to_anchor)
+        .transpose()?;
+    let base_nibbles = anchor.as_ref().map_or(0, SnapshotAnchor::required_nibbles);
+
+    let mut hashes: Vec<[u8; 32]> = Vec::new();
+    for (i, account) in result.accounts.iter().enumerate() {
+        let address: [u8; 20] = crate::parse_hex_array(&account.address)?;
+        let hash = keccak256(&address);
+        if crate::parse_hex_array::<32>(&account.hashed_key)? != hash {
+            return Err(format!(
+                "Level {}: hashed key does not match address {}",
+                i + 1,
+                account.address
+            ));
+        }
+
+        let reference = match (i, &anchor) {
+            (0, None) => None,
+            (0, Some(anchor)) => Some(&anchor.key),
+            _ => Some(&hashes[i - 1]),
+        };
+        if let Some(reference) = reference {
+            let required = base_nibbles + i;
+            if !has_nibble_prefix(&hash, reference, required) {
+                return Err(format!(
+                    "Level {}: account hash shares fewer than {required} nibbles with the previous level",
+                    i + 1
+                ));
+            }
+            let shared = count_shared_nibbles(&hash, reference);
+            if result.exact && shared != required {
+                return Err(format!(
+                    "Level {}: account hash shares {shared} nibbles with the previous level, not exactly {required}",
+                    i + 1
+                ));
+            }
+            if account.shared_nibbles != shared {
+                return Err(format!(
+                    "Level {}: reports {} shared nibbles, but shares {shared}",
+                    i + 1,
+                    account.shared_nibbles
+                ));
+            }
+        }
+        hashes.push(hash);
+    }
+
+    if let Some(report) = verify_account_branch_trie(&hashes, anchor.as_ref()) {
+        info!(
+            "Trie path to the deepest account: {} nodes ({} branch nodes)",
+            report.depth, report.branch_nodes
+        );
+        if let Some(proof) = &report.proof {
+            info!("Account proof: {}", proof.describe());
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the account trie holding every mined contract and auxiliary account
+///
+/// Contracts are inserted with nonce 1 and auxiliaries with a balance of 1 wei, so that
+/// both would survive EIP-161 empty-account cleanup.
+pub fn build_account_trie(accounts: &[MinedContract]) -> Trie {
+    let contract_account = mpt::account_rlp(1, 0, &mpt::EMPTY_ROOT, &mpt::EMPTY_CODE_HASH);
+    let auxiliary_account = mpt::account_rlp(0, 1, &mpt::EMPTY_ROOT, &mpt::EMPTY_CODE_HASH);
+
+    let mut trie = Trie::new();
+    for (contract, auxiliaries) in accounts {
+        trie.insert(&keccak256(contract), contract_account.clone());
+        for auxiliary in auxiliaries {
+            trie.insert(&keccak256(auxiliary), auxiliary_account.clone());
+        }
+    }
+    trie
+}
+
+/// Log the range of verified contract depths and the largest contract proof
+fn log_trie_depths(reports: &[TrieReport]) {
+    let depths = reports.iter().map(|report| report.depth);
+
+    if let (Some(min), Some(max), Some(report)) =
+        (depths.clone().min(), depths.max(), reports.first())
+    {
+        info!("Verified account trie depth: {min}..={max} nodes per contract");
+        info!("Account trie root: {}", report.root);
+    }
+    if let Some(proof) = reports
+        .iter()
+        .filter_map(|report| report.proof.as_ref())
+        .max_by_key(|proof| proof.total_bytes)
+    {
+        info!("Largest contract proof: {}", proof.describe());
+    }
+}
+
+/// Load a CREATE2 mining result from a JSON file
+pub fn load_results(path: &str) -> Result<Create2MiningResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid CREATE2 result JSON: {e}"))
+}
+
+/// Load a checkpoint (or a finished result) and check that it was mined with the same
+/// deployer, salts, salt guard, init code hash and depth, so that resuming continues the same
+/// run
+#[allow(clippy::too_many_arguments)]
+pub fn load_checkpoint(
+    path: &str,
+    deployer: &[u8; 20],
+    salts: &SaltRange,
+    guard: &SaltGuard,
+    contract_nibbles: usize,
+    contract_target: Option<&[u8; 32]>,
+    exact: bool,
+    init_code_hash: &[u8; 32],
+    target_depth: usize,
+    num_contracts: usize,
+) -> Result<Create2MiningResult, String> {
+    let result = load_results(path)?;
+
+    if crate::parse_hex_array::<20>(&result.deployer)? != *deployer {
+        return Err(format!(
+            "Checkpoint was mined for deployer {}, not 0x{}",
+            result.deployer,
+            hex::encode(deployer)
+        ));
+    }
+    if crate::parse_hex_array::<32>(&result.init_code_hash)? != *init_code_hash {
+        return Err(format!(
+            "Checkpoint was mined for init code hash {}, not 0x{}",
+            result.init_code_hash,
+            hex::encode(init_code_hash)
+        ));
+    }
+    if result.salt_prefix != salts.prefix_hex() || result.salt_start != salts.start {
+        return Err(format!(
+            "Checkpoint was mined with salt prefix {} from counter {}, not {} from {}",
+            result.salt_prefix,
+            result.salt_start,
+            salts.prefix_hex(),
+            salts.start
+        ));
+    }
+    if result.salt_guard()? != *guard {
+        return Err("Checkpoint was mined for a different CreateX caller or chain id".to_string());
+    }
+    if result.target_depth != target_depth {
+        return Err(format!(
+            "Checkpoint was mined for depth {}, not {target_depth}",
+            result.target_depth
+        ));
+    }
+    if result.contract_nibbles != contract_nibbles {
+        return Err(format!(
+            "Checkpoint was mined with {} shared contract nibbles, not {contract_nibbles}",
+            result.contract_nibbles
+        ));
+    }
+    if let Some(target) = contract_target
+        && result.contract_target != Some(format!("0x{}", hex::encode(target)))
+    {
+        return Err(format!(
+            "Checkpoint was mined towards a different contract target ({})",
+            result
+                .contract_target
+                .as_deref()
+                .unwrap_or("the first contract")
+        ));
+    }
+    if result.exact != exact {
+        return Err(format!(
+            "Checkpoint was mined {} exact prefixes",
+            if result.exact { "with" } else { "without" }
+        ));
+    }
+    if result.contracts.len() > num_contracts {
+        return Err(format!(
+            "Checkpoint already holds {} contracts, more than the {num_contracts} requested",
+            result.contracts.len()
+        ));
+    }
+    // Mining picks up after the last salt, so salts must be in counter order: consecutive
+    // when used in order, increasing when searched
+    let mut previous: Option<u64> = None;
+    for (index, contract) in result.contracts.iter().enumerate() {
+        let salt = crate::parse_hex_array::<32>(&contract.salt)?;
+        let in_order = match (salts.counter_of(&salt), previous) {
+            (Some(counter), _) if contract_nibbles == 0 => counter == salts.start + index as u64,
+            (Some(counter), Some(previous)) => counter > previous,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if !in_order {
+            return Err(format!(
+                "Checkpoint entry {index} has salt {}, which does not continue the salt range",
+                contract.salt
+            ));
+        }
+        previous = salts.counter_of(&salt);
+    }
+
+    let target = result
+        .contract_target
+        .as_deref()
+        .map(crate::parse_hex_array::<32>)
+        .transpose()?;
+    verify_contracts(
+        deployer,
+        guard,
+        init_code_hash,
+        target_depth,
+        contract_nibbles,
+        target.as_ref(),
+        exact,
+        &result.contracts,
+    )?;
+    Ok(result)
+}
+
+/// Check that every contract address follows from the deployer, salt and init code hash,
+/// and that each auxiliary account's hash shares its depth in nibbles with the contract hash
+pub fn verify_results(result: &Create2MiningResult) -> Result<(), String> {
+    let deployer: [u8; 20] = crate::parse_hex_array(&result.deployer)?;
+    let init_code_hash: [u8; 32] = crate::parse_hex_array(&result.init_code_hash)?;
+
+    if result.contracts.len() != result.num_contracts {
+        return Err(format!(
+            "Result claims {} contracts but contains {}",
+            result.num_contracts,
+            result.contracts.len()
+        ));
+    }
+
+    let target = result
+        .contract_target
+        .as_deref()
+        .map(crate::parse_hex_array::<32>)
+        .transpose()?;
+    let mined_accounts = verify_contracts(
+        &deployer,
+        &result.salt_guard()?,
+        &init_code_hash,
+        result.target_depth,
+        result.contract_nibbles,
+        target.as_ref(),
+        result.exact,
+        &result.contracts,
+    )?;
+
+    // Report the depths the accounts really produce together
+    let trie = build_account_trie(&mined_accounts);
+    let reports: Vec<TrieReport> = mined_accounts
+        .iter()
+        .map(|(contract_address, _)| trie.report(&keccak256(contract_address)))
+        .collect();
+    log_trie_depths(&reports);
+
+    Ok(())
+}
+
+/// Load a CREATE mining result from a JSON file
+pub fn load_create_results(path: &str) -> Result<CreateMiningResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid CREATE result JSON: {e}"))
+}
+
+/// Check that every contract address follows from its deployer and nonce, that mined
+/// deployer keys derive their deployers, and that the auxiliaries share the required prefixes
+pub fn verify_create_results(result: &CreateMiningResult) -> Result<(), String> {
+    if result.contracts.len() != result.num_contracts {
+        return Err(format!(
+            "Result claims {} contracts but contains {}",
+            result.num_contracts,
+            result.contracts.len()
+        ));
+    }
+    let target = result
+        .contract_target
+        .as_deref()
+        .map(crate::parse_hex_array::<32>)
+        .transpose()?;
+    if result.contract_nibbles > 0 && target.is_none() {
+        return Err("Contracts were mined towards a prefix but no target is recorded".into());
+    }
+
+    let secp = Secp256k1::signing_only();
+    let mut mined_accounts = Vec::new();
+    for contract in &result.contracts {
+        let label = format!("Nonce {} of {}", contract.nonce, contract.deployer);
+        let deployer: [u8; 20] = crate::parse_hex_array(&contract.deployer)?;
+        if let Some(key) = &contract.deployer_key
+            && keys::address_of(&secp, &keys::parse_secret(key)?) != deployer
+        {
+            return Err(format!(
+                "{label}: private key does not belong to the deployer"
+            ));
+        }
+
+        let contract_address: [u8; 20] = crate::parse_hex_array(&contract.contract_address)?;
+        let expected = calculate_create_address(&deployer, contract.nonce);
+        if expected != contract_address {
+            return Err(format!(
+                "{label}: contract address {} does not match CREATE address 0x{}",
+                contract.contract_address,
+                hex::encode(expected)
+            ));
+        }
+
+        let auxiliaries = verify_auxiliaries(
+            &label,
+            &contract_address,
+            result.contract_nibbles,
+            target.as_ref(),
+            result.target_depth,
+            result.exact,
+            &contract.auxiliary_accounts,
+            &contract.auxiliary_keys,
+            &contract.auxiliary_shared_nibbles,
+        )?;
+        mined_accounts.push((contract_address, auxiliaries));
+    }
+
+    let trie = build_account_trie(&mined_accounts);
+    let reports: Vec<TrieReport> = mined_accounts
+        .iter()
+        .map(|(contract_address, _)| trie.report(&keccak256(contract_address)))
+        .collect();
+    log_trie_depths(&reports);
+
+    Ok(())
+}
+
+/// Recompute each contract's CREATE2 address from its guarded salt and check its
+/// auxiliaries, returning the parsed accounts
+///
+/// With `contract_nibbles > 0` every contract hash must share that prefix with the target,
+/// and the auxiliaries cover the levels below it.
+#[allow(clippy::too_many_arguments)]
+fn verify_contracts(
+    deployer: &[u8; 20],
+    guard: &SaltGuard,
+    init_code_hash: &[u8; 32],
+    target_depth: usize,
+    contract_nibbles: usize,
+    contract_target: Option<&[u8; 32]>,
+    exact: bool,
+    contracts: &[ContractWithAuxiliaries],
+) -> Result<Vec<MinedContract>, String> {
+    let mut mined_accounts = Vec::new();
+    if contract_nibbles > 0 && contract_target.is_none() && !contracts.is_empty() {
+        return Err("Contracts were mined towards a prefix but no target is recorded".into());
+    }
+
+    for contract in contracts {
+        let contract_address: [u8; 20] = crate::parse_hex_array(&contract.contract_address)?;
+        let salt: [u8; 32] = crate::parse_hex_array(&contract.salt)?;
+        let expected = calculate_create2_address(deployer, &guard.apply(&salt)?, init_code_hash);
+        if expected != contract_address {
+            return Err(format!(
+                "Salt {}: contract address {} does not match CREATE2 address 0x{}",
+                contract.salt,
+                contract.contract_address,
+                hex::encode(expected)
+            ));
+        }
+
+        let auxiliaries = verify_auxiliaries(
+            &format!("Salt {}", contract.salt),
+            &contract_address,
+            contract_nibbles,
+            contract_target,
+            target_depth,
+            exact,
+            &contract.auxiliary_accounts,
+            &contract.auxiliary_keys,
+            &contract.auxiliary_shared_nibbles,
+        )?;
+        mined_accounts.push((contract_address, auxiliaries));
+    }
+
+    Ok(mined_accounts)
+}
+
+/// Check a contract's hash against the shared target prefix, and its auxiliaries (and their
+/// keys and shared nibble counts, if any) against the levels below it, returning the parsed
+/// auxiliaries
+#[allow(clippy::too_many_arguments)]
+fn verify_auxiliaries(
+    label: &str,
+    contract_address: &[u8; 20],
+    contract_nibbles: usize,
+    contract_target: Option<&[u8; 32]>,
+    target_depth: usize,
+    exact: bool,
+    auxiliary_accounts: &[String],
+    auxiliary_keys: &[String],
+    auxiliary_shared_nibbles: &[usize],
+) -> Result<Vec<[u8; 20]>, String> {
+    let contract_hash = keccak256(contract_address);
+    if let Some(target) = contract_target
+        && !has_nibble_prefix(&contract_hash, target, contract_nibbles)
+    {
+        return Err(format!(
+            "{label}: contract hash shares fewer than {contract_nibbles} nibbles with the target"
+        ));
+    }
+
+    let expected_auxiliaries = target_depth.saturating_sub(contract_nibbles);
+    if auxiliary_accounts.len() != expected_auxiliaries {
+        return Err(format!(
+            "{label}: expected {expected_auxiliaries} auxiliary accounts, found {}",
+            auxiliary_accounts.len()
+        ));
+    }
+
+    // Keys are optional, but when present there is one per auxiliary that derives it
+    if !auxiliary_keys.is_empty() {
+        if auxiliary_keys.len() != auxiliary_accounts.len() {
+            return Err(format!(
+                "{label}: {} auxiliary keys for {} auxiliary accounts",
+                auxiliary_keys.len(),
+                auxiliary_accounts.len()
+            ));
+        }
+        let secp = Secp256k1::signing_only();
+        for (key, auxiliary) in auxiliary_keys.iter().zip(auxiliary_accounts) {
+            let address = keys::address_of(&secp, &keys::parse_secret(key)?);
+            if crate::parse_hex_array::<20>(auxiliary)? != address {
+                return Err(format!(
+                    "{label}: private key does not belong to auxiliary {auxiliary}"
+                ));
+            }
+        }
+    }
+
+    let mut auxiliaries = Vec::new();
+    for (i, auxiliary) in auxiliary_accounts.iter().enumerate() {
+        let auxiliary: [u8; 20] = crate::parse_hex_array(auxiliary)?;
+        let required = contract_nibbles + i + 1;
+        let hash = keccak256(&auxiliary);
+        if !prefix_matches(&hash, &contract_hash, required, exact) {
+            return Err(format!(
+                "{label}: auxiliary 0x{} shares {} nibbles with the contract hash, {} {required}",
+                hex::encode(auxiliary),
+                count_shared_nibbles(&hash, &contract_hash),
+                if exact { "not exactly" } else { "fewer than" }
+            ));
+        }
+        auxiliaries.push(auxiliary);
+    }
+
+    // Older results carry no shared nibble counts, newer ones must report the real ones
+    if !auxiliary_shared_nibbles.is_empty()
+        && auxiliary_shared_nibbles != shared_nibbles_with(contract_address, &auxiliaries)
+    {
+        return Err(format!(
+            "{label}: reported auxiliary shared nibbles do not match the auxiliary hashes"
+        ));
+    }
+
+    Ok(auxiliaries)
+}
+
+/// Calculate the CREATE address `keccak256(rlp([sender, nonce]))[12..]`
+pub fn calculate_create_address(sender: &[u8; 20], nonce: u64) -> [u8; 20] {
+    let hash = keccak256(&rlp::encode_list(&[
+        rlp::encode_bytes(sender),
+        rlp::encode_u64(nonce),
+    ]));
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Nibbles each auxiliary's hash shares with the contract hash
+fn shared_nibbles_with(contract_address: &[u8; 20], auxiliaries: &[[u8; 20]]) -> Vec<usize> {
+    let contract_hash = keccak256(contract_address);
+    auxiliaries
+        .iter()
+        .map(|auxiliary| count_shared_nibbles(&keccak256(auxiliary), &contract_hash))
+        .collect()
+}
+
+/// Address of the first contract an account deploys
+fn first_create_address(sender: &[u8; 20]) -> [u8; 20] {
+    calculate_create_address(sender, 0)
+}
+
 /// Calculate CREATE2 address
-fn calculate_create2_address(
+pub fn calculate_create2_address(
     deployer: &[u8; 20],
-    salt: u32,
+    salt: &[u8; 32],
     init_code_hash: &[u8; 32],
 ) -> [u8; 20] {
     let mut data = Vec::with_capacity(85);
@@ -146,10 +1442,8 @@ fn calculate_create2_address(
     // Deployer address (20 bytes)
     data.extend_from_slice(deployer);
 
-    // Salt (32 bytes) - convert u32 to 32 bytes with zero padding
-    let mut salt_bytes = [0u8; 32];
-    salt_bytes[28..32].copy_from_slice(&salt.to_be_bytes());
-    data.extend_from_slice(&salt_bytes);
+    // Salt (32 bytes)
+    data.extend_from_slice(salt);
 
     // Init code hash (32 bytes)
     data.extend_from_slice(init_code_hash);
@@ -162,150 +1456,536 @@ fn calculate_create2_address(
     address
 }
 
-/// Mine auxiliary accounts for a single contract
-fn mine_auxiliaries_for_contract(
+/// Auxiliary searches of one contract running on the engine, one per depth
+struct PendingAuxiliaries {
+    contract_address: [u8; 20],
+    searches: Vec<JobHandle<([u8; 20], Option<SecretKey>)>>,
+}
+
+impl PendingAuxiliaries {
+    /// Wait for every auxiliary, returning the contract with its auxiliaries and their
+    /// private keys (empty unless mined as EOAs)
+    fn wait(self) -> MinedAuxiliaries {
+        let mut auxiliaries = Vec::new();
+        let mut keys = Vec::new();
+        for search in &self.searches {
+            let (auxiliary, key) = search.wait().expect("Failed to find auxiliary");
+            debug!("  Found: 0x{}", hex::encode(&auxiliary[..4]));
+            auxiliaries.push(auxiliary);
+            keys.extend(key);
+        }
+        (self.contract_address, auxiliaries, keys)
+    }
+}
+
+/// Queue the auxiliary accounts of a single contract, one per depth from `first_depth` on,
+/// mined as EOAs with private keys when `eoa_keys` is set
+///
+/// All depths only depend on the contract hash, so they are searched side by side.
+fn submit_auxiliaries(
+    engine: &MiningEngine,
     contract_address: &[u8; 20],
+    first_depth: usize,
     target_depth: usize,
-    num_threads: usize,
-) -> Vec<[u8; 20]> {
-    let mut auxiliaries = Vec::new();
-
+    eoa_keys: bool,
+    exact: bool,
+) -> PendingAuxiliaries {
     // Calculate the hash of the contract address - this is the key in the account trie
     let contract_hash = keccak256(contract_address);
 
-    for depth in 1..=target_depth {
-        debug!("  Mining auxiliary at depth {depth}/{target_depth}");
+    let secp = Secp256k1::signing_only();
 
-        // Mine an account whose hash shares 'depth' nibbles with the contract hash
-        let auxiliary = mine_account_with_hash_prefix(&contract_hash, depth, num_threads);
+    let searches = (first_depth..=target_depth)
+        .map(|depth| {
+            debug!("  Mining auxiliary at depth {depth}/{target_depth}");
+
+            // Mine an account whose hash shares 'depth' nibbles with the contract hash
+            let prefix = Prefix::new(contract_hash, depth, exact);
+            submit_auxiliary_search(engine, eoa_keys, &secp, move |hash| prefix.matches(hash))
+        })
+        .collect();
 
+    PendingAuxiliaries {
+        contract_address: *contract_address,
+        searches,
+    }
+}
+
+/// Queue a search for one auxiliary account whose hash satisfies `predicate`, as an EOA with
+/// its private key when `eoa_keys` is set
+fn submit_auxiliary_search<P>(
+    engine: &MiningEngine,
+    eoa_keys: bool,
+    secp: &Secp256k1<secp256k1::SignOnly>,
+    predicate: P,
+) -> JobHandle<([u8; 20], Option<SecretKey>)>
+where
+    P: Fn(&[u8; 32]) -> bool + Send + Sync + 'static,
+{
+    let hashed = |(address, _): &([u8; 20], Option<SecretKey>)| keccak256(address);
+    if eoa_keys {
+        let eoa = search::random_eoa(secp.clone());
+        search::submit(
+            engine,
+            AUXILIARY_PRIORITY,
+            search::KEY_BATCH_SIZE,
+            move |rng, attempt| {
+                let (address, key) = eoa(rng, attempt);
+                (address, Some(key))
+            },
+            hashed,
+            predicate,
+        )
+    } else {
+        search::submit(
+            engine,
+            AUXILIARY_PRIORITY,
+            search::HASH_BATCH_SIZE,
+            |rng, attempt| (search::random_address(rng, attempt), None),
+            hashed,
+            predicate,
+        )
+    }
+}
+
+/// Mine the auxiliaries of many contracts (one per depth from `first_depth` on) from a single
+/// stream of candidates, and hand each contract's auxiliaries to `on_complete` with its index
+/// as soon as all of them are found
+///
+/// Every candidate is checked against the unfilled targets of all contracts at once, so a
+/// candidate that misses one contract can fill another: across `n` contracts, filling a depth
+/// costs about `16^depth * ln(n)` hashes instead of `16^depth * n`.
+fn mine_auxiliaries_batch(
+    engine: &MiningEngine,
+    contract_addresses: &[[u8; 20]],
+    first_depth: usize,
+    target_depth: usize,
+    eoa_keys: bool,
+    exact: bool,
+    mut on_complete: impl FnMut(usize, MinedAuxiliaries),
+) {
+    let levels = (target_depth + 1).saturating_sub(first_depth);
+    let index = Arc::new(TargetIndex::new(
+        contract_addresses.iter().map(|a| keccak256(a)).collect(),
+        first_depth,
+        target_depth,
+        exact,
+    ));
+    let mut found = vec![vec![None; levels]; contract_addresses.len()];
+    let mut missing = vec![levels; contract_addresses.len()];
+    if levels == 0 {
+        for (i, address) in contract_addresses.iter().enumerate() {
+            on_complete(i, (*address, Vec::new(), Vec::new()));
+        }
+        return;
+    }
+
+    info!(
+        "Batch mining {} auxiliaries for {} contracts",
+        index.remaining(),
+        contract_addresses.len()
+    );
+    let secp = Secp256k1::signing_only();
+    let mut attempts = 0u64;
+    while index.remaining() > 0 {
+        let unfilled = Arc::clone(&index);
+        let search = submit_auxiliary_search(engine, eoa_keys, &secp, move |hash| {
+            unfilled.matches_any(hash)
+        });
+        let candidate = search.wait().expect("Failed to find auxiliary");
+        attempts += search.attempts();
+
+        let target = index
+            .claim(&keccak256(&candidate.0))
+            .expect("a hit fills an unfilled target");
         debug!(
-            "  Found: 0x{} (hash shares {} nibbles)",
-            hex::encode(&auxiliary[..4]),
-            depth
+            "  Found: 0x{} (contract {}, depth {})",
+            hex::encode(&candidate.0[..4]),
+            target.contract + 1,
+            target.depth
         );
+        found[target.contract][target.depth - first_depth] = Some(candidate);
+        missing[target.contract] -= 1;
 
-        auxiliaries.push(auxiliary);
+        if missing[target.contract] == 0 {
+            let (auxiliaries, keys) = found[target.contract]
+                .iter_mut()
+                .map(|auxiliary| auxiliary.take().expect("every depth is filled"))
+                .unzip::<_, _, Vec<_>, Vec<_>>();
+            let contract_address = contract_addresses[target.contract];
+            on_complete(
+                target.contract,
+                (
+                    contract_address,
+                    auxiliaries,
+                    keys.into_iter().flatten().collect(),
+                ),
+            );
+        }
     }
 
-    auxiliaries
+    // Independent searches need 16^d candidates per auxiliary, 16/15 times that when exact
+    let expected: f64 = (first_depth..=target_depth)
+        .map(|depth| 16f64.powi(depth as i32) * if exact { 16.0 / 15.0 } else { 1.0 })
+        .sum::<f64>()
+        * contract_addresses.len() as f64;
+    info!(
+        "Batch tested about {attempts} candidates, independent searches expect about {expected:.0}"
+    );
 }
 
-/// Mine an account whose hash shares exactly `depth` nibbles with the target hash
+/// Search salt counters from `first_counter` on for a CREATE2 address whose hash shares
+/// `nibbles` nibbles with the target, returning the first matching counter found
+#[allow(clippy::too_many_arguments)]
+fn mine_salt_for_prefix(
+    engine: &MiningEngine,
+    deployer: &[u8; 20],
+    salts: &SaltRange,
+    guard: &SaltGuard,
+    first_counter: u64,
+    init_code_hash: &[u8; 32],
+    target_hash: &[u8; 32],
+    nibbles: usize,
+) -> u64 {
+    let (deployer, salts, guard, init_code_hash) =
+        (*deployer, salts.clone(), *guard, *init_code_hash);
+    let prefix = Prefix::new(*target_hash, nibbles, false);
+
+    let search = search::submit(
+        engine,
+        CONTRACT_PRIORITY,
+        search::HASH_BATCH_SIZE,
+        move |_, attempt| first_counter + attempt,
+        move |&counter| {
+            let salt = guard
+                .apply(&salts.salt_for_counter(counter))
+                .expect("salt range checked against the guard");
+            keccak256(&calculate_create2_address(
+                &deployer,
+                &salt,
+                &init_code_hash,
+            ))
+        },
+        move |hash| prefix.matches(hash),
+    );
+
+    let counter = search.wait().expect("Failed to find salt");
+    debug!(
+        "  Salt counter {counter} after {} candidates",
+        search.attempts()
+    );
+    counter
+}
+
+/// Mine an account whose hash shares `depth` nibbles with the target hash, and with `exact`
+/// differs in the nibble after them
 fn mine_account_with_hash_prefix(
     target_hash: &[u8; 32],
     depth: usize,
+    exact: bool,
     num_threads: usize,
 ) -> [u8; 20] {
-    let result = Arc::new(Mutex::new(None));
-    let found = Arc::new(Mutex::new(false));
-
-    let handles: Vec<_> = (0..num_threads)
-        .map(|thread_id| {
-            let result_clone = Arc::clone(&result);
-            let found_clone = Arc::clone(&found);
-            let target_hash_copy = *target_hash;
-
-            thread::spawn(move || {
-                mine_hash_worker(
-                    thread_id,
-                    &target_hash_copy,
-                    depth,
-                    result_clone,
-                    found_clone,
-                );
-            })
-        })
-        .collect();
+    let prefix = Prefix::new(*target_hash, depth, exact);
+    search::submit(
+        &MiningEngine::shared(num_threads),
+        AUXILIARY_PRIORITY,
+        search::HASH_BATCH_SIZE,
+        search::random_address,
+        |address| keccak256(address),
+        move |hash| prefix.matches(hash),
+    )
+    .wait()
+    .expect("Failed to find account")
+}
 
-    for handle in handles {
-        handle.join().unwrap();
-    }
+/// Queue a search for a deployer key whose first contract (nonce 0) hashes to the prefix
+///
+/// Each attempt derives a public key, so this is far slower per attempt than mining plain
+/// addresses.
+fn submit_deployer_search(
+    engine: &MiningEngine,
+    prefix: Prefix,
+) -> JobHandle<([u8; 20], SecretKey)> {
+    search::submit(
+        engine,
+        CONTRACT_PRIORITY,
+        search::KEY_BATCH_SIZE,
+        search::random_eoa(Secp256k1::signing_only()),
+        |(deployer, _)| keccak256(&first_create_address(deployer)),
+        move |hash| prefix.matches(hash),
+    )
+}
 
-    result.lock().unwrap().expect("Failed to find account")
+/// Compute Keccak256 hash
+pub(crate) fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
 }
 
-/// Worker thread for hash-based mining
-fn mine_hash_worker(
-    thread_id: usize,
-    target_hash: &[u8; 32],
-    required_nibbles: usize,
-    result: Arc<Mutex<Option<[u8; 20]>>>,
-    found: Arc<Mutex<bool>>,
-) {
-    let mut rng = rand::thread_rng();
-    let mut attempts = 0u64;
-    const BATCH_SIZE: u64 = 1000;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    loop {
-        // Check if another thread found a result
-        if attempts % BATCH_SIZE == 0 && *found.lock().unwrap() {
-            break;
-        }
+    /// Example 5 from EIP-1014
+    #[test]
+    fn test_create2_address_eip1014() {
+        let deployer =
+            crate::parse_hex_array("0x00000000000000000000000000000000deadbeef").unwrap();
+        let salt = crate::parse_hex_array(
+            "0x00000000000000000000000000000000000000000000000000000000cafebabe",
+        )
+        .unwrap();
+        let address =
+            calculate_create2_address(&deployer, &salt, &keccak256(&[0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(
+            hex::encode(address),
+            "60f3f640a8508fc6a86d45df051962668e1e8ac7"
+        );
+    }
 
-        attempts += 1;
-        if attempts % 1000000 == 0 {
-            debug!(
-                "Thread {} (depth {}): {} million attempts",
-                thread_id,
-                required_nibbles,
-                attempts / 1000000
-            );
-        }
+    #[test]
+    fn test_create_address() {
+        let sender = crate::parse_hex_array("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        assert_eq!(
+            hex::encode(calculate_create_address(&sender, 0)),
+            "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"
+        );
+        assert_eq!(
+            hex::encode(calculate_create_address(&sender, 1)),
+            "343c43a37d37dff08ae8c4a11544c718abb4fcf8"
+        );
+        // Nonces from 128 on are encoded as byte strings
+        assert_eq!(
+            hex::encode(calculate_create_address(&sender, 200)),
+            "eb7facd118466c9acbcb4ee964a0ac0b0b2ef256"
+        );
+    }
 
-        // Generate random address
-        let mut address = [0u8; 20];
-        rng.fill(&mut address);
+    #[test]
+    fn test_salt_range() {
+        let salts = SaltRange::new(vec![0xab; 20], 0xff, 2).unwrap();
+        let salt = salts.salt_for_counter(0x100);
+        assert_eq!(salt[..20], [0xab; 20]);
+        assert_eq!(salt[20..], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0]);
 
-        // Hash the address - this is how it's indexed in the account trie
-        let address_hash = keccak256(&address);
+        // Counter 256 no longer fits below a 31-byte prefix
+        assert!(SaltRange::new(vec![0; 31], 0, 256).is_ok());
+        assert!(SaltRange::new(vec![0; 31], 0, 257).is_err());
+        assert!(SaltRange::new(vec![0; 33], 0, 1).is_err());
 
-        // Check if the hash matches the required prefix
-        if has_hash_prefix(&address_hash, target_hash, required_nibbles) {
-            let mut found_lock = found.lock().unwrap();
-            if !*found_lock {
-                *found_lock = true;
-                let mut result_lock = result.lock().unwrap();
-                *result_lock = Some(address);
-                debug!("Thread {thread_id} found match after {attempts} attempts");
-            }
-            break;
-        }
+        // Prefix bytes past byte 24 share the low word with the counter
+        let salts = SaltRange::new(vec![0xab; 26], 1, 2).unwrap();
+        let salt = salts.salt_for_counter(1);
+        assert_eq!(salt[24..], [0xab, 0xab, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(salts.counter_of(&salt), Some(1));
+        assert_eq!(salts.counter_of(&salts.salt_for_counter(0)), None);
+        assert_eq!(salts.counter_of(&[0xcd; 32]), None);
     }
-}
 
-/// Check if two hashes share the specified number of nibbles as prefix
-fn has_hash_prefix(hash_a: &[u8; 32], hash_b: &[u8; 32], nibbles: usize) -> bool {
-    if nibbles == 0 {
-        return true;
+    /// A CREATE2 checkpoint only resumes the run it came from, and mining continues with the
+    /// next salt of the range
+    #[test]
+    fn test_resume_create2_from_checkpoint() {
+        let path = std::env::temp_dir()
+            .join(format!("create2_resume_{}.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let (deployer, init_code) = ([0x77; 20], [0x60, 0x00, 0xf3]);
+        let init_code_hash = keccak256(&init_code);
+        // A prefix reaching into the counter's low word, as --salt-prefix allows
+        let salts = SaltRange::new(vec![0xab; 26], 5, 3).unwrap();
+        let guard = SaltGuard::Plain;
+        let mine = |num_contracts, resumed| {
+            mine_create2_accounts(
+                deployer,
+                &salts,
+                &guard,
+                0,
+                None,
+                false,
+                false,
+                false,
+                num_contracts,
+                1,
+                2,
+                &init_code,
+                &path,
+                resumed,
+            )
+        };
+        mine(2, None);
+
+        let load =
+            |deployer: &[u8; 20], guard: &SaltGuard, hash: &[u8; 32], depth, exact, count| {
+                load_checkpoint(
+                    &path, deployer, &salts, guard, 0, None, exact, hash, depth, count,
+                )
+            };
+        assert!(load(&[0x78; 20], &guard, &init_code_hash, 1, false, 3).is_err());
+        assert!(load(&deployer, &guard, &[0; 32], 1, false, 3).is_err());
+        assert!(load(&deployer, &guard, &init_code_hash, 2, false, 3).is_err());
+        assert!(load(&deployer, &guard, &init_code_hash, 1, true, 3).is_err());
+        assert!(load(&deployer, &guard, &init_code_hash, 1, false, 1).is_err());
+        let createx = SaltGuard::Createx {
+            caller: None,
+            chain_id: None,
+        };
+        assert!(load(&deployer, &createx, &init_code_hash, 1, false, 3).is_err());
+
+        let result = load(&deployer, &guard, &init_code_hash, 1, false, 3).unwrap();
+        let salts_of = |result: &Create2MiningResult| -> Vec<String> {
+            result.contracts.iter().map(|c| c.salt.clone()).collect()
+        };
+        let mined_salts = salts_of(&result);
+
+        // Salts out of counter order do not continue the range
+        let mut swapped = load_results(&path).unwrap();
+        swapped.contracts.swap(0, 1);
+        checkpoint::save(&path, &swapped).unwrap();
+        assert!(load(&deployer, &guard, &init_code_hash, 1, false, 3).is_err());
+
+        mine(3, Some(result));
+        let extended = load_results(&path).unwrap();
+        verify_results(&extended).unwrap();
+        assert_eq!(extended.contracts.len(), 3);
+        assert_eq!(salts_of(&extended)[..2], mined_salts);
+        assert_eq!(
+            extended.contracts[2].salt,
+            format!("0x{}", hex::encode(salts.salt_for_counter(7)))
+        );
+        checkpoint::remove(&path);
     }
 
-    let full_bytes = nibbles / 2;
-    let has_half_byte = nibbles % 2 == 1;
+    #[test]
+    fn test_salt_search_reaches_target() {
+        let salts = SaltRange::unbounded(vec![0x42; 12], 0).unwrap();
+        let (deployer, init_code_hash, target) = ([1u8; 20], [2u8; 32], [0xab; 32]);
+        let engine = MiningEngine::new(2);
+        let counter = mine_salt_for_prefix(
+            &engine,
+            &deployer,
+            &salts,
+            &SaltGuard::Plain,
+            0,
+            &init_code_hash,
+            &target,
+            2,
+        );
 
-    // Check full bytes must match
-    for i in 0..full_bytes {
-        if hash_a[i] != hash_b[i] {
-            return false;
-        }
+        let address =
+            calculate_create2_address(&deployer, &salts.salt_for_counter(counter), &init_code_hash);
+        assert!(has_nibble_prefix(&keccak256(&address), &target, 2));
+        assert_eq!(
+            salts.counter_of(&salts.salt_for_counter(counter)),
+            Some(counter)
+        );
+    }
+
+    #[test]
+    fn test_deployer_mining_reaches_target() {
+        let target = [0x5a; 32];
+        let (deployer, key) =
+            submit_deployer_search(&MiningEngine::new(2), Prefix::new(target, 2, false))
+                .wait()
+                .unwrap();
+        assert_eq!(keys::address_of(&Secp256k1::signing_only(), &key), deployer);
+        assert!(has_nibble_prefix(
+            &keccak256(&first_create_address(&deployer)),
+            &target,
+            2
+        ));
     }
 
-    // Check the half byte if needed
-    if has_half_byte && full_bytes < 32 {
-        let mask = 0xF0; // Check only the high nibble
-        if (hash_a[full_bytes] & mask) != (hash_b[full_bytes] & mask) {
-            return false;
+    #[test]
+    fn test_key_mining_reaches_target() {
+        let contract = [0x5a; 20];
+        let (_, auxiliaries, secrets) =
+            submit_auxiliaries(&MiningEngine::new(2), &contract, 1, 2, true, false).wait();
+        assert_eq!(secrets.len(), auxiliaries.len());
+
+        let secp = Secp256k1::signing_only();
+        for (key, auxiliary) in secrets.iter().zip(&auxiliaries) {
+            assert_eq!(keys::address_of(&secp, key), *auxiliary);
         }
+        let accounts: Vec<String> = auxiliaries
+            .iter()
+            .map(|a| format!("0x{}", hex::encode(a)))
+            .collect();
+        let mut secrets: Vec<String> = secrets.iter().map(keys::format_secret).collect();
+        let check = |secrets: &[String]| {
+            verify_auxiliaries("t", &contract, 0, None, 2, false, &accounts, secrets, &[])
+        };
+        assert!(check(&secrets).is_ok());
+        secrets.reverse();
+        assert!(check(&secrets).is_err());
     }
 
-    true
-}
+    #[test]
+    fn test_exact_auxiliaries_form_branch_nodes() {
+        let contract = [7u8; 20];
+        let (_, auxiliaries, _) =
+            submit_auxiliaries(&MiningEngine::new(2), &contract, 1, 3, false, true).wait();
+        assert_eq!(shared_nibbles_with(&contract, &auxiliaries), vec![1, 2, 3]);
 
-/// Compute Keccak256 hash
-fn keccak256(data: &[u8]) -> [u8; 32] {
-    let mut hasher = Keccak::v256();
-    let mut output = [0u8; 32];
-    hasher.update(data);
-    hasher.finalize(&mut output);
-    output
+        let format = |accounts: &[[u8; 20]]| -> Vec<String> {
+            accounts
+                .iter()
+                .map(|a| format!("0x{}", hex::encode(a)))
+                .collect()
+        };
+        let check = |accounts: &[[u8; 20]], shared: &[usize]| {
+            verify_auxiliaries(
+                "t",
+                &contract,
+                0,
+                None,
+                3,
+                true,
+                &format(accounts),
+                &[],
+                shared,
+            )
+        };
+        assert!(check(&auxiliaries, &[1, 2, 3]).is_ok());
+        assert!(check(&auxiliaries, &[1, 2, 4]).is_err());
+
+        // The deepest auxiliary also shares the first nibble with the contract, but not exactly
+        let collapsed = [auxiliaries[2], auxiliaries[1], auxiliaries[2]];
+        assert!(check(&collapsed, &[]).is_err());
+    }
+
+    #[test]
+    fn test_batch_fills_every_contract() {
+        let contracts = [[1u8; 20], [2u8; 20], [3u8; 20]];
+        let mut mined = Vec::new();
+        mine_auxiliaries_batch(
+            &MiningEngine::new(2),
+            &contracts,
+            1,
+            2,
+            false,
+            true,
+            |index, auxiliaries| mined.push((index, auxiliaries)),
+        );
+
+        mined.sort_by_key(|(index, _)| *index);
+        assert_eq!(mined.len(), 3);
+        for (index, (contract, auxiliaries, keys)) in mined {
+            assert_eq!(contract, contracts[index]);
+            assert_eq!(shared_nibbles_with(&contract, &auxiliaries), vec![1, 2]);
+            assert!(keys.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_counter_salts_still_load() {
+        let contract: ContractWithAuxiliaries = serde_json::from_str(
+            r#"{"salt": 7, "contract_address": "0x00", "auxiliary_accounts": []}"#,
+        )
+        .unwrap();
+        assert_eq!(contract.salt, format!("0x{}07", "00".repeat(31)));
+    }
 }
@@ -0,0 +1,515 @@
+//! # Binary Tree Module
+//!
+//! Key derivation of the unified binary tree proposed in EIP-7864, and mining of storage slots
+//! or accounts whose stems form a deep branch in it. Every leaf of the binary tree is keyed by
+//! `sha256(address32 || tree_index)` truncated to a 31-byte stem, plus a one-byte sub-index,
+//! so 256 leaves share a stem: the account header with the first 64 storage slots, or 256
+//! consecutive main storage slots. Stems hang below a trie of internal nodes with two
+//! children each, so depth is counted in bits and every level only has to share one more bit
+//! with the previous one instead of a whole nibble.
+//!
+//! ## Key Functions
+//! - `tree_key`: Derives the tree key of an address, tree index and sub-index
+//! - `account_key` / `storage_key`: Tree keys of an account's basic data and of a storage slot
+//! - `mine_stem_branch`: Mines a chain of keys whose stems share an increasing bit prefix
+//! - `write_results` / `verify_results`: JSON output and re-verification
+
+use crate::engine::MiningEngine;
+use crate::layout::{LayoutInfo, SlotLayout};
+use crate::search::{self, Prefix, count_shared_bits, has_bit_prefix};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::time::Instant;
+
+/// Sub-index of the basic data leaf (version, code size, nonce and balance) of an account
+pub const BASIC_DATA_LEAF_KEY: u8 = 0;
+/// Sub-index of storage slot 0 in the account header stem
+const HEADER_STORAGE_OFFSET: u8 = 64;
+/// Sub-index of the first code chunk in the account header stem
+const CODE_OFFSET: u8 = 128;
+/// Length of a stem in bits, and so the most bits two distinct stems can share
+pub const STEM_BITS: usize = 248;
+
+/// Which keys a stem branch is mined for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StemKind {
+    /// Storage slots of one contract, derived from the mined mapping keys by the slot layout
+    Storage,
+    /// Accounts, keyed by the stem of their basic data leaf
+    Account,
+}
+
+/// How the tree key of a mined candidate is derived
+#[derive(Clone, Debug)]
+pub enum StemSource {
+    Storage {
+        contract: [u8; 20],
+        layout: SlotLayout,
+    },
+    Account,
+}
+
+impl StemSource {
+    pub fn kind(&self) -> StemKind {
+        match self {
+            StemSource::Storage { .. } => StemKind::Storage,
+            StemSource::Account => StemKind::Account,
+        }
+    }
+
+    /// Tree key of a mined mapping key or address
+    pub fn tree_key(&self, candidate: &[u8; 20]) -> [u8; 32] {
+        match self {
+            StemSource::Storage { contract, layout } => {
+                storage_key(contract, &layout.storage_key(candidate))
+            }
+            StemSource::Account => account_key(candidate),
+        }
+    }
+}
+
+/// A single level of a stem branch
+#[derive(Clone, Debug)]
+pub struct StemLevel {
+    /// Mined mapping key (storage stems) or address (account stems)
+    pub key: [u8; 20],
+    pub tree_key: [u8; 32],
+    pub time_taken: f64,
+}
+
+/// Result structure for stem mining
+#[derive(Serialize, Deserialize)]
+pub struct StemBranchResult {
+    pub kind: StemKind,
+    /// Contract whose storage holds the mined slots (storage stems only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    /// Where the mined keys' values are stored (storage stems only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutInfo>,
+    pub depth: usize,
+    /// Whether every level shares exactly its required bits with the previous one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+    pub total_time: f64,
+    /// Internal nodes above the deepest stem in a binary tree holding the mined keys
+    pub stem_depth: usize,
+    pub stems: Vec<MinedStem>,
+}
+
+/// Serializable form of a single `StemLevel`
+#[derive(Serialize, Deserialize)]
+pub struct MinedStem {
+    pub key: String,
+    /// Storage slot the mapping key maps to (storage stems only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_slot: Option<String>,
+    pub tree_key: String,
+    pub time_taken: f64,
+    /// Bits the stem shares with the previous level (0 for the first level)
+    pub shared_bits: usize,
+}
+
+impl StemBranchResult {
+    /// Build the serializable result from a mined branch
+    pub fn from_branch(
+        source: &StemSource,
+        branch: &[StemLevel],
+        total_time: f64,
+        exact: bool,
+    ) -> Self {
+        let (contract, layout) = match source {
+            StemSource::Storage { contract, layout } => (
+                Some(format!("0x{}", hex::encode(contract))),
+                Some(layout.info()),
+            ),
+            StemSource::Account => (None, None),
+        };
+        let stems = branch
+            .iter()
+            .enumerate()
+            .map(|(i, level)| MinedStem {
+                key: format!("0x{}", hex::encode(level.key)),
+                storage_slot: match source {
+                    StemSource::Storage { layout, .. } => {
+                        Some(format!("0x{}", hex::encode(layout.storage_key(&level.key))))
+                    }
+                    StemSource::Account => None,
+                },
+                tree_key: format!("0x{}", hex::encode(level.tree_key)),
+                time_taken: level.time_taken,
+                shared_bits: match i {
+                    0 => 0,
+                    _ => count_shared_bits(&branch[i - 1].tree_key, &level.tree_key),
+                },
+            })
+            .collect();
+
+        StemBranchResult {
+            kind: source.kind(),
+            contract,
+            layout,
+            depth: branch.len(),
+            exact,
+            total_time,
+            stem_depth: deepest_stem_depth(branch),
+            stems,
+        }
+    }
+
+    /// Rebuild the key derivation the branch was mined with
+    pub fn source(&self) -> Result<StemSource, String> {
+        match self.kind {
+            StemKind::Storage => {
+                let (Some(contract), Some(layout)) = (&self.contract, &self.layout) else {
+                    return Err("Storage stem result lacks its contract or layout".to_string());
+                };
+                Ok(StemSource::Storage {
+                    contract: crate::parse_hex_array(contract)?,
+                    layout: layout.to_layout()?,
+                })
+            }
+            StemKind::Account => Ok(StemSource::Account),
+        }
+    }
+
+    /// Rebuild the mined branch from a deserialized result
+    pub fn to_branch(&self) -> Result<Vec<StemLevel>, String> {
+        self.stems
+            .iter()
+            .map(|stem| {
+                Ok(StemLevel {
+                    key: crate::parse_hex_array(&stem.key)?,
+                    tree_key: crate::parse_hex_array(&stem.tree_key)?,
+                    time_taken: stem.time_taken,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Tree key of `sub_index` below the stem of `address` and `tree_index` (a big-endian word):
+/// `sha256(address32 || tree_index as 32 little-endian bytes)[..31] || sub_index`
+pub fn tree_key(address: &[u8; 20], tree_index: &[u8; 32], sub_index: u8) -> [u8; 32] {
+    let mut little_endian = *tree_index;
+    little_endian.reverse();
+
+    let mut hasher = Sha256::new();
+    hasher.update([0u8; 12]);
+    hasher.update(address);
+    hasher.update(little_endian);
+
+    let mut key: [u8; 32] = hasher.finalize().into();
+    key[31] = sub_index;
+    key
+}
+
+/// Tree key of the basic data leaf of `address`, on the stem of its account header
+pub fn account_key(address: &[u8; 20]) -> [u8; 32] {
+    tree_key(address, &[0; 32], BASIC_DATA_LEAF_KEY)
+}
+
+/// Tree key of storage slot `slot` (a big-endian word) of `address`
+///
+/// The first 64 slots live on the account header stem. Every other slot is offset by 256^31,
+/// so its stem is picked by `256^30 + slot / 256` and its sub-index by the slot's last byte.
+pub fn storage_key(address: &[u8; 20], slot: &[u8; 32]) -> [u8; 32] {
+    let header_slots = CODE_OFFSET - HEADER_STORAGE_OFFSET;
+    if slot[..31].iter().all(|&byte| byte == 0) && slot[31] < header_slots {
+        return tree_key(address, &[0; 32], HEADER_STORAGE_OFFSET + slot[31]);
+    }
+
+    let mut tree_index = [0u8; 32];
+    tree_index[1..].copy_from_slice(&slot[..31]);
+    let (byte, carry) = tree_index[1].overflowing_add(1);
+    tree_index[1] = byte;
+    tree_index[0] = u8::from(carry);
+    tree_key(address, &tree_index, slot[31])
+}
+
+/// Internal nodes above the deepest stem of the branch: one per bit it shares with its
+/// closest other stem, plus the node where they split
+pub fn deepest_stem_depth(branch: &[StemLevel]) -> usize {
+    let stems: Vec<&[u8]> = branch.iter().map(|level| &level.tree_key[..31]).collect();
+    (0..stems.len())
+        .filter_map(|i| {
+            stems
+                .iter()
+                .enumerate()
+                .filter(|&(j, stem)| j != i && *stem != stems[i])
+                .map(|(j, _)| count_shared_bits(&branch[i].tree_key, &branch[j].tree_key))
+                .max()
+        })
+        .max()
+        .map_or(0, |shared| shared.min(STEM_BITS - 1) + 1)
+}
+
+/// Mine a chain of keys whose stems share an increasing number of bits
+///
+/// Level N shares N-1 bits with the previous level, so each level sits one internal node
+/// deeper than the last. With `exact` the next bit must differ, so no level skips past the
+/// internal node it is meant to create.
+pub fn mine_stem_branch(
+    source: &StemSource,
+    target_depth: usize,
+    num_threads: usize,
+    exact: bool,
+) -> Vec<StemLevel> {
+    let mut branch: Vec<StemLevel> = Vec::new();
+    info!("Starting binary tree stem mining for {target_depth} levels");
+
+    for level in 0..target_depth {
+        let level_start = Instant::now();
+
+        let key = match branch.last() {
+            None => {
+                let mut key = [0u8; 20];
+                fastrand::fill(&mut key);
+                key
+            }
+            Some(previous) => {
+                info!(
+                    "Mining level {}/{} (requires {} matching bits)",
+                    level + 1,
+                    target_depth,
+                    level
+                );
+                let prefix = Prefix::bits(previous.tree_key, level, exact);
+                let derivation = source.clone();
+                let search = search::submit(
+                    &MiningEngine::shared(num_threads),
+                    0,
+                    search::HASH_BATCH_SIZE,
+                    search::random_address,
+                    move |candidate| derivation.tree_key(candidate),
+                    move |tree_key| prefix.matches(tree_key),
+                );
+                match search.wait() {
+                    Some(key) => key,
+                    None => {
+                        info!("Failed to find a key for level {} - stopping", level + 1);
+                        break;
+                    }
+                }
+            }
+        };
+
+        let tree_key = source.tree_key(&key);
+        branch.push(StemLevel {
+            key,
+            tree_key,
+            time_taken: level_start.elapsed().as_secs_f64(),
+        });
+        info!(
+            "Level {} found in {:.2} seconds - Key: 0x{}, Stem: 0x{}...",
+            level + 1,
+            level_start.elapsed().as_secs_f64(),
+            hex::encode(key),
+            hex::encode(&tree_key[..4])
+        );
+    }
+
+    branch
+}
+
+/// Log the mined stems and the depth of the deepest one
+pub fn print_results(result: &StemBranchResult) {
+    info!("");
+    info!("╔════════════════════════════════════════════════════════════════════════╗");
+    info!("║                       STEM MINING RESULTS                              ║");
+    info!("╚════════════════════════════════════════════════════════════════════════╝");
+    info!("");
+    info!("Total depth achieved: {} levels", result.depth);
+    if let Some(contract) = &result.contract {
+        info!("Contract: {contract}");
+    }
+    info!(
+        "Deepest stem: {} internal nodes below the root",
+        result.stem_depth
+    );
+    info!("Total time taken: {:.2} seconds", result.total_time);
+    info!("");
+
+    for (i, stem) in result.stems.iter().enumerate() {
+        info!("Level {}:", i + 1);
+        info!("  Key:      {}", stem.key);
+        if let Some(slot) = &stem.storage_slot {
+            info!("  Slot:     {slot}");
+        }
+        info!("  Tree key: {}", stem.tree_key);
+        if i > 0 {
+            info!("  Shares {} bits with previous level", stem.shared_bits);
+        }
+    }
+    info!("");
+}
+
+/// Write a stem branch result to a JSON file
+pub fn write_results(result: &StemBranchResult, output_path: &str) {
+    match serde_json::to_string_pretty(result) {
+        Ok(json) => {
+            if let Err(e) = fs::write(output_path, json) {
+                log::error!("Failed to write JSON: {e}");
+            } else {
+                info!("Results saved to: {output_path}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to serialize to JSON: {e}");
+        }
+    }
+}
+
+/// Load a stem branch result from a JSON file
+pub fn load_results(path: &str) -> Result<StemBranchResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid stem branch JSON: {e}"))
+}
+
+/// Check that every tree key follows from its mined key, that each level shares at least
+/// (or with `exact`, exactly) its required bits with the previous one, and that the recorded
+/// shared bits and stem depth match
+pub fn verify_results(result: &StemBranchResult) -> Result<(), String> {
+    let source = result.source()?;
+    let branch = result.to_branch()?;
+    if branch.len() != result.depth {
+        return Err(format!(
+            "Result claims depth {} but contains {} levels",
+            result.depth,
+            branch.len()
+        ));
+    }
+
+    for (i, level) in branch.iter().enumerate() {
+        if source.tree_key(&level.key) != level.tree_key {
+            return Err(format!(
+                "Level {}: tree key 0x{} does not follow from key 0x{}",
+                i + 1,
+                hex::encode(level.tree_key),
+                hex::encode(level.key)
+            ));
+        }
+        let Some(previous) = i.checked_sub(1).map(|j| &branch[j]) else {
+            continue;
+        };
+
+        let shared = count_shared_bits(&previous.tree_key, &level.tree_key);
+        if !has_bit_prefix(&previous.tree_key, &level.tree_key, i) {
+            return Err(format!(
+                "Level {}: stem shares {shared} bits with the previous level, fewer than {i}",
+                i + 1
+            ));
+        }
+        if result.exact && shared != i {
+            return Err(format!(
+                "Level {}: stem shares {shared} bits with the previous level, not exactly {i}",
+                i + 1
+            ));
+        }
+        if result.stems[i].shared_bits != shared {
+            return Err(format!(
+                "Level {}: reports {} shared bits, but shares {shared}",
+                i + 1,
+                result.stems[i].shared_bits
+            ));
+        }
+    }
+
+    let stem_depth = deepest_stem_depth(&branch);
+    if stem_depth != result.stem_depth {
+        return Err(format!(
+            "Result claims a stem depth of {} but the stems give {stem_depth}",
+            result.stem_depth
+        ));
+    }
+    info!("Deepest stem: {stem_depth} internal nodes below the root");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_keys_share_stems() {
+        let address = [0x42; 20];
+        let slot = |last: u8| {
+            let mut slot = [0u8; 32];
+            slot[31] = last;
+            slot
+        };
+
+        // The first 64 slots sit next to the basic data leaf on the account header stem
+        let header = account_key(&address);
+        let slot_5 = storage_key(&address, &slot(5));
+        assert_eq!(header[..31], slot_5[..31]);
+        assert_eq!(slot_5[31], HEADER_STORAGE_OFFSET + 5);
+
+        // Main storage slots share a stem in groups of 256
+        let slot_64 = storage_key(&address, &slot(64));
+        let slot_255 = storage_key(&address, &slot(255));
+        assert_ne!(header[..31], slot_64[..31]);
+        assert_eq!(slot_64[..31], slot_255[..31]);
+        assert_eq!(slot_255[31], 255);
+        let mut slot_256 = slot(0);
+        slot_256[30] = 1;
+        assert_ne!(storage_key(&address, &slot_256)[..31], slot_64[..31]);
+    }
+
+    /// Vectors computed independently with the `get_tree_key`,
+    /// `get_tree_key_for_basic_data` and `get_tree_key_for_storage_slot` pseudo-code of
+    /// EIP-7864, using sha256 as the tree hash
+    #[test]
+    fn test_known_tree_keys() {
+        let address: [u8; 20] =
+            crate::parse_hex_array("0x1234567890abcdef1234567890abcdef12345678").unwrap();
+        let hex_key = |key: [u8; 32]| format!("0x{}", hex::encode(key));
+        let slot = |value: &str| -> [u8; 32] { crate::layout::parse_word(value).unwrap() };
+
+        assert_eq!(
+            hex_key(account_key(&address)),
+            "0x2ad5164512d367db8571faeb9c3f80dc98ba376b595c626d9a1da02b34086300"
+        );
+        // Tree indices are hashed little-endian
+        assert_eq!(
+            hex_key(tree_key(&address, &slot("0x0102"), 7)),
+            "0x12474cc25183c8e0e67168e54adb9ea6832c58fc9b1ec1fb21b9aea5c9677407"
+        );
+        assert_eq!(
+            hex_key(storage_key(&address, &slot("0x5"))),
+            "0x2ad5164512d367db8571faeb9c3f80dc98ba376b595c626d9a1da02b34086345"
+        );
+        assert_eq!(
+            hex_key(storage_key(&address, &slot("0x1234"))),
+            "0xb16078583291b0147c5480a542d567f0d012d9f24d0f4969026bb7ad351b8034"
+        );
+        // The main storage offset carries into the top byte of the tree index
+        assert_eq!(
+            hex_key(storage_key(&address, &[0xff; 32])),
+            "0x20bd99ba9d64b20028c5114e8bef315cfdc22891c3b656095511ed772b192aff"
+        );
+    }
+
+    #[test]
+    fn test_mined_stems_verify() {
+        let source = StemSource::Storage {
+            contract: [0x11; 20],
+            layout: SlotLayout::default(),
+        };
+        let branch = mine_stem_branch(&source, 6, 2, true);
+        assert_eq!(branch.len(), 6);
+
+        let result = StemBranchResult::from_branch(&source, &branch, 0.0, true);
+        assert_eq!(result.stems[5].shared_bits, 5);
+        assert_eq!(result.stem_depth, 6);
+        verify_results(&result).unwrap();
+
+        let mut tampered = StemBranchResult::from_branch(&source, &branch, 0.0, true);
+        tampered.contract = Some(format!("0x{}", "22".repeat(20)));
+        assert!(verify_results(&tampered).is_err());
+    }
+}
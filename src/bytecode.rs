@@ -0,0 +1,515 @@
+//! # Bytecode Module
+//!
+//! This module assembles EVM initcode and runtime code for the mined storage branch directly,
+//! without going through solc. The output only depends on the mined slots, so the CREATE2
+//! init code hash is reproducible across machines and compiler versions.
+//!
+//! ## Key Functions
+//! - `initcode`: Builds creation code that SSTOREs every mined slot and deploys a runtime
+//! - `runtime_code`: Builds the deployed code for the selected `RuntimeKind`
+//! - `initial_storage`: The storage the initcode writes, for contracts placed in genesis
+//! - `touch_code`: Runtime that loads a list of accounts from the state trie
+//! - `Assembler`: Minimal assembler with labels used to lay out the runtimes
+
+use crate::account_miner::keccak256;
+use crate::layout::SlotLayout;
+use crate::storage_miner::{ERC20_BALANCES_SLOT, StorageSlot, calculate_storage_slot};
+use std::collections::HashMap;
+
+/// EVM opcodes used by the generated code
+#[allow(dead_code)]
+pub mod op {
+    pub const STOP: u8 = 0x00;
+    pub const ADD: u8 = 0x01;
+    pub const SUB: u8 = 0x03;
+    pub const LT: u8 = 0x10;
+    pub const EQ: u8 = 0x14;
+    pub const AND: u8 = 0x16;
+    pub const SHR: u8 = 0x1c;
+    pub const SHA3: u8 = 0x20;
+    pub const BALANCE: u8 = 0x31;
+    pub const CALLER: u8 = 0x33;
+    pub const CALLDATALOAD: u8 = 0x35;
+    pub const CODECOPY: u8 = 0x39;
+    pub const POP: u8 = 0x50;
+    pub const MSTORE: u8 = 0x52;
+    pub const SLOAD: u8 = 0x54;
+    pub const SSTORE: u8 = 0x55;
+    pub const JUMP: u8 = 0x56;
+    pub const JUMPI: u8 = 0x57;
+    pub const JUMPDEST: u8 = 0x5b;
+    pub const PUSH1: u8 = 0x60;
+    pub const PUSH2: u8 = 0x61;
+    pub const PUSH20: u8 = 0x73;
+    pub const PUSH32: u8 = 0x7f;
+    pub const DUP1: u8 = 0x80;
+    pub const DUP2: u8 = 0x81;
+    pub const DUP3: u8 = 0x82;
+    pub const SWAP1: u8 = 0x90;
+    pub const SWAP2: u8 = 0x91;
+    pub const RETURN: u8 = 0xf3;
+    pub const REVERT: u8 = 0xfd;
+}
+
+/// Largest initcode a creation transaction or CREATE2 may run since Shanghai (EIP-3860)
+pub const MAX_INITCODE_SIZE: usize = 49_152;
+
+/// Storage slot of `totalSupply`, matching the Solidity template's layout
+const TOTAL_SUPPLY_SLOT: u64 = 2;
+
+/// Supply minted to the deployer by the ERC20 runtime's constructor (1 billion tokens, 18 decimals)
+const INITIAL_SUPPLY: u128 = 1_000_000_000 * 10u128.pow(18);
+
+/// Which runtime code the initcode deploys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum RuntimeKind {
+    /// No runtime code: the contract only holds the mined storage
+    None,
+    /// `attack(uint256)` and `getDeepest()` on the deepest mined slot
+    Attack,
+    /// Minimal ERC20 (`balanceOf`, `totalSupply`, `transfer`) plus `attack`/`getDeepest`
+    Erc20,
+}
+
+/// Minimal EVM assembler with forward-referencable labels
+#[derive(Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    labels: HashMap<&'static str, usize>,
+    fixups: Vec<(usize, &'static str)>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a single opcode
+    pub fn op(&mut self, opcode: u8) -> &mut Self {
+        self.code.push(opcode);
+        self
+    }
+
+    /// Append several opcodes
+    pub fn ops(&mut self, opcodes: &[u8]) -> &mut Self {
+        self.code.extend_from_slice(opcodes);
+        self
+    }
+
+    /// Push a big-endian value using the shortest PUSHn (PUSH1 for zero, no PUSH0)
+    pub fn push(&mut self, value: &[u8]) -> &mut Self {
+        let first = value.iter().position(|&b| b != 0).unwrap_or(value.len());
+        let trimmed = if first == value.len() {
+            &[0u8][..]
+        } else {
+            &value[first..]
+        };
+        assert!(trimmed.len() <= 32, "PUSH operand longer than 32 bytes");
+
+        self.code.push(op::PUSH1 + trimmed.len() as u8 - 1);
+        self.code.extend_from_slice(trimmed);
+        self
+    }
+
+    /// Push a full 32-byte word with PUSH32, keeping leading zeros
+    pub fn push32(&mut self, word: &[u8; 32]) -> &mut Self {
+        self.code.push(op::PUSH32);
+        self.code.extend_from_slice(word);
+        self
+    }
+
+    pub fn push_u64(&mut self, value: u64) -> &mut Self {
+        self.push(&value.to_be_bytes())
+    }
+
+    /// Push the offset of a label as PUSH2, resolved in `assemble`
+    pub fn push_label(&mut self, label: &'static str) -> &mut Self {
+        self.code.push(op::PUSH2);
+        self.fixups.push((self.code.len(), label));
+        self.code.extend_from_slice(&[0, 0]);
+        self
+    }
+
+    /// Define a label at the current offset and emit its JUMPDEST
+    pub fn label(&mut self, label: &'static str) -> &mut Self {
+        let previous = self.labels.insert(label, self.code.len());
+        assert!(previous.is_none(), "label `{label}` defined twice");
+        self.op(op::JUMPDEST)
+    }
+
+    /// Append raw bytes (e.g. a runtime appended after the initcode)
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.code.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Resolve label references and return the bytecode
+    pub fn assemble(mut self) -> Vec<u8> {
+        for (position, label) in &self.fixups {
+            let target = *self
+                .labels
+                .get(label)
+                .unwrap_or_else(|| panic!("undefined label `{label}`"));
+            let target = u16::try_from(target).expect("code larger than PUSH2 range");
+            self.code[*position..*position + 2].copy_from_slice(&target.to_be_bytes());
+        }
+        self.code
+    }
+}
+
+/// First four bytes of keccak256 of a function signature
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Build the creation code: SSTORE 1 into every mined slot, then deploy the chosen runtime
+///
+/// Fails when the code exceeds the EIP-3860 initcode limit, which long branches (around 1300
+/// slots) and forests do, or when the runtime cannot serve `layout` (see `runtime_code`).
+pub fn initcode(
+    branch: &[StorageSlot],
+    kind: RuntimeKind,
+    layout: &SlotLayout,
+) -> Result<Vec<u8>, String> {
+    let runtime = runtime_code(branch, kind, layout)?;
+    let mut asm = Assembler::new();
+
+    for slot in branch {
+        asm.push_u64(1).push32(&slot.storage_key).op(op::SSTORE);
+    }
+
+    if kind == RuntimeKind::Erc20 {
+        // Mint the initial supply to the deployer, as the Solidity constructor does
+        let supply = INITIAL_SUPPLY.to_be_bytes();
+        asm.push(&supply).push_u64(TOTAL_SUPPLY_SLOT).op(op::SSTORE);
+        asm.push(&supply);
+        emit_balance_slot(&mut asm, |asm| {
+            asm.op(op::CALLER);
+        });
+        asm.op(op::SSTORE);
+    }
+
+    // The copy sequence below has a fixed length, so the runtime offset is known up front:
+    // PUSH2 len, PUSH2 offset, PUSH1 0, CODECOPY, PUSH2 len, PUSH1 0, RETURN
+    const COPY_SEQUENCE_LEN: usize = 3 + 3 + 2 + 1 + 3 + 2 + 1;
+    let size = match runtime.len() {
+        0 => asm.len() + 1,
+        runtime_len => asm.len() + COPY_SEQUENCE_LEN + runtime_len,
+    };
+    if size > MAX_INITCODE_SIZE {
+        return Err(format!(
+            "Initcode for {} slots would be {size} bytes, more than the EIP-3860 limit of {MAX_INITCODE_SIZE}",
+            branch.len()
+        ));
+    }
+
+    if runtime.is_empty() {
+        asm.op(op::STOP);
+        return Ok(asm.assemble());
+    }
+
+    let runtime_len = u16::try_from(runtime.len())
+        .map_err(|_| format!("Runtime of {} bytes overflows PUSH2", runtime.len()))?;
+    let runtime_offset = u16::try_from(asm.len() + COPY_SEQUENCE_LEN)
+        .map_err(|_| format!("Runtime offset {} overflows PUSH2", asm.len()))?;
+
+    asm.op(op::PUSH2)
+        .raw(&runtime_len.to_be_bytes())
+        .op(op::PUSH2)
+        .raw(&runtime_offset.to_be_bytes())
+        .ops(&[op::PUSH1, 0, op::CODECOPY])
+        .op(op::PUSH2)
+        .raw(&runtime_len.to_be_bytes())
+        .ops(&[op::PUSH1, 0, op::RETURN])
+        .raw(&runtime);
+
+    Ok(asm.assemble())
+}
+
+/// Storage `initcode(branch, kind)` leaves behind when `deployer` runs it: 1 in every mined
+/// slot, plus the ERC20 runtime's total supply and the deployer's balance
+pub fn initial_storage(
+    branch: &[StorageSlot],
+    kind: RuntimeKind,
+    deployer: &[u8; 20],
+) -> Vec<([u8; 32], [u8; 32])> {
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    let mut storage: Vec<_> = branch.iter().map(|slot| (slot.storage_key, one)).collect();
+
+    if kind == RuntimeKind::Erc20 {
+        let mut supply = [0u8; 32];
+        supply[16..].copy_from_slice(&INITIAL_SUPPLY.to_be_bytes());
+        let mut total_supply_slot = [0u8; 32];
+        total_supply_slot[24..].copy_from_slice(&TOTAL_SUPPLY_SLOT.to_be_bytes());
+        storage.push((total_supply_slot, supply));
+        storage.push((
+            calculate_storage_slot(deployer, ERC20_BALANCES_SLOT),
+            supply,
+        ));
+    }
+    storage
+}
+
+/// Runtime that reads the balance of every address, so a call loads each account from the
+/// state trie without changing it
+pub fn touch_code(addresses: &[[u8; 20]]) -> Vec<u8> {
+    let mut asm = Assembler::new();
+    for address in addresses {
+        // PUSH20 keeps leading zero bytes, unlike `push`
+        asm.op(op::PUSH20).raw(address).ops(&[op::BALANCE, op::POP]);
+    }
+    asm.op(op::STOP);
+    asm.assemble()
+}
+
+/// Build the deployed runtime code for the given kind
+///
+/// The ERC20 runtime keeps its balances in a plain `mapping(address => uint256)` at slot 0, so
+/// it is refused for any other `layout`: its `balanceOf` and `transfer` would miss the mined
+/// slots.
+pub fn runtime_code(
+    branch: &[StorageSlot],
+    kind: RuntimeKind,
+    layout: &SlotLayout,
+) -> Result<Vec<u8>, String> {
+    if kind == RuntimeKind::Erc20 && !layout.is_plain_address_mapping(ERC20_BALANCES_SLOT) {
+        return Err(format!(
+            "The erc20 runtime keeps balances in slot {ERC20_BALANCES_SLOT} mapping keys, not {}; use the attack runtime",
+            layout.describe()
+        ));
+    }
+
+    let deepest = branch
+        .last()
+        .map(|slot| slot.storage_key)
+        .unwrap_or([0; 32]);
+
+    let mut functions: Vec<(&str, &'static str)> = Vec::new();
+    match kind {
+        RuntimeKind::None => return Ok(Vec::new()),
+        RuntimeKind::Attack => {}
+        RuntimeKind::Erc20 => {
+            functions.push(("balanceOf(address)", "balance_of"));
+            functions.push(("totalSupply()", "total_supply"));
+            functions.push(("transfer(address,uint256)", "transfer"));
+        }
+    }
+    functions.push(("attack(uint256)", "attack"));
+    functions.push(("getDeepest()", "get_deepest"));
+
+    let mut asm = Assembler::new();
+
+    // Dispatcher: selector = calldata[0..4]
+    asm.ops(&[op::PUSH1, 0, op::CALLDATALOAD, op::PUSH1, 0xe0, op::SHR]);
+    for (signature, label) in &functions {
+        asm.op(op::DUP1)
+            .push(&selector(signature))
+            .op(op::EQ)
+            .push_label(label)
+            .op(op::JUMPI);
+    }
+    asm.push_label("revert").op(op::JUMP);
+
+    // attack(uint256 value): sstore(deepest, value)
+    asm.label("attack")
+        .op(op::POP)
+        .ops(&[op::PUSH1, 0x04, op::CALLDATALOAD])
+        .push32(&deepest)
+        .ops(&[op::SSTORE, op::STOP]);
+
+    // getDeepest(): return sload(deepest)
+    asm.label("get_deepest")
+        .op(op::POP)
+        .push32(&deepest)
+        .op(op::SLOAD);
+    emit_return_word(&mut asm);
+
+    if kind == RuntimeKind::Erc20 {
+        // balanceOf(address owner): return balanceOf[owner]
+        asm.label("balance_of").op(op::POP);
+        emit_balance_slot(&mut asm, emit_address_arg);
+        asm.op(op::SLOAD);
+        emit_return_word(&mut asm);
+
+        // totalSupply(): return sload(2)
+        asm.label("total_supply")
+            .op(op::POP)
+            .push_u64(TOTAL_SUPPLY_SLOT)
+            .op(op::SLOAD);
+        emit_return_word(&mut asm);
+
+        // transfer(address to, uint256 amount)
+        asm.label("transfer").op(op::POP);
+        emit_balance_slot(&mut asm, |asm| {
+            asm.op(op::CALLER);
+        });
+        // [from_slot] -> [from_slot, from_balance, amount]
+        asm.ops(&[op::DUP1, op::SLOAD, op::PUSH1, 0x24, op::CALLDATALOAD]);
+        // Revert if from_balance < amount
+        asm.ops(&[op::DUP1, op::DUP3, op::LT])
+            .push_label("revert")
+            .op(op::JUMPI);
+        // sstore(from_slot, from_balance - amount) -> [amount]
+        asm.ops(&[
+            op::DUP1,
+            op::SWAP2,
+            op::SUB,
+            op::DUP3,
+            op::SSTORE,
+            op::SWAP1,
+            op::POP,
+        ]);
+        // sstore(to_slot, sload(to_slot) + amount)
+        emit_balance_slot(&mut asm, emit_address_arg);
+        asm.ops(&[
+            op::DUP1,
+            op::SLOAD,
+            op::DUP3,
+            op::ADD,
+            op::SWAP1,
+            op::SSTORE,
+            op::POP,
+        ]);
+        asm.push_u64(1);
+        emit_return_word(&mut asm);
+    }
+
+    asm.label("revert")
+        .ops(&[op::PUSH1, 0, op::DUP1, op::REVERT]);
+
+    Ok(asm.assemble())
+}
+
+/// Leave keccak256(key . ERC20_BALANCES_SLOT) on the stack, with `key` pushed by `emit_key`
+fn emit_balance_slot(asm: &mut Assembler, emit_key: impl FnOnce(&mut Assembler)) {
+    emit_key(asm);
+    asm.ops(&[op::PUSH1, 0, op::MSTORE])
+        .push_u64(ERC20_BALANCES_SLOT)
+        .ops(&[op::PUSH1, 0x20, op::MSTORE])
+        .ops(&[op::PUSH1, 0x40, op::PUSH1, 0, op::SHA3]);
+}
+
+/// Push the first calldata argument masked to 20 bytes
+fn emit_address_arg(asm: &mut Assembler) {
+    asm.ops(&[op::PUSH1, 0x04, op::CALLDATALOAD])
+        .push(&[0xff; 20])
+        .op(op::AND);
+}
+
+/// Return the word on top of the stack
+fn emit_return_word(asm: &mut Assembler) {
+    asm.ops(&[
+        op::PUSH1,
+        0,
+        op::MSTORE,
+        op::PUSH1,
+        0x20,
+        op::PUSH1,
+        0,
+        op::RETURN,
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(key_byte: u8) -> StorageSlot {
+        StorageSlot {
+            address: [0; 20],
+            storage_key: [key_byte; 32],
+            depth: 0,
+            time_taken: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(selector("attack(uint256)"), [0x64, 0xdd, 0x89, 0x1a]);
+        assert_eq!(selector("getDeepest()"), [0xd9, 0x33, 0x9c, 0xc3]);
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+    }
+
+    /// Storage-only initcode is just PUSH1 1 PUSH32 key SSTORE per slot followed by STOP
+    #[test]
+    fn test_storage_only_initcode() {
+        let code = initcode(
+            &[slot(0xaa), slot(0xbb)],
+            RuntimeKind::None,
+            &SlotLayout::default(),
+        )
+        .unwrap();
+
+        let mut expected = Vec::new();
+        for byte in [0xaa, 0xbb] {
+            expected.extend_from_slice(&[op::PUSH1, 1, op::PUSH32]);
+            expected.extend_from_slice(&[byte; 32]);
+            expected.push(op::SSTORE);
+        }
+        expected.push(op::STOP);
+
+        assert_eq!(code, expected);
+    }
+
+    /// The runtime is appended verbatim at the offset the CODECOPY reads from
+    #[test]
+    fn test_initcode_embeds_runtime() {
+        let branch = [slot(0x11), slot(0x22)];
+        let layout = SlotLayout::default();
+        for kind in [RuntimeKind::Attack, RuntimeKind::Erc20] {
+            let runtime = runtime_code(&branch, kind, &layout).unwrap();
+            let code = initcode(&branch, kind, &layout).unwrap();
+
+            assert!(code.ends_with(&runtime));
+            let copy = code.len() - runtime.len() - 15;
+            let offset = u16::from_be_bytes([code[copy + 4], code[copy + 5]]) as usize;
+            assert_eq!(offset, code.len() - runtime.len());
+            assert_eq!(
+                code,
+                initcode(&branch, kind, &layout).unwrap(),
+                "initcode must be deterministic"
+            );
+        }
+    }
+
+    /// Branches too long for one creation transaction are an error, not a panic
+    #[test]
+    fn test_initcode_size_limit() {
+        let branch: Vec<StorageSlot> = (0..2000).map(|i| slot(i as u8)).collect();
+        let layout = SlotLayout::default();
+        for kind in [RuntimeKind::None, RuntimeKind::Erc20] {
+            let error = initcode(&branch, kind, &layout).unwrap_err();
+            assert!(error.contains("EIP-3860"), "{error}");
+        }
+        let code = initcode(&branch[..1000], RuntimeKind::Erc20, &layout).unwrap();
+        assert!(code.len() <= MAX_INITCODE_SIZE);
+    }
+
+    /// The ERC20 runtime only serves balances at slot 0
+    #[test]
+    fn test_erc20_runtime_needs_plain_balances() {
+        let branch = [slot(0x11)];
+        let mut base_slot = [0u8; 32];
+        base_slot[31] = 9;
+        let layout = SlotLayout::new(
+            Default::default(),
+            Default::default(),
+            base_slot,
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+        assert!(runtime_code(&branch, RuntimeKind::Erc20, &layout).is_err());
+        assert!(initcode(&branch, RuntimeKind::Erc20, &layout).is_err());
+        assert!(runtime_code(&branch, RuntimeKind::Attack, &layout).is_ok());
+    }
+}
@@ -0,0 +1,106 @@
+//! # Checkpoint Module
+//!
+//! Long mining runs write their progress next to the output file after every finished level,
+//! or at most every `INTERVAL` while contracts finish, so a crash only loses the work in flight.
+//! A checkpoint has the same format as the final result, which means `--resume` accepts
+//! either one.
+//!
+//! ## Key Functions
+//! - `path_for`: Checkpoint path belonging to an output file
+//! - `save`: Atomically replaces the checkpoint with the current progress
+//! - `Periodic::save_if_due`: Saves only once the interval since the last save has passed
+//! - `remove`: Deletes the checkpoint once the final result is written
+
+use serde::Serialize;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two checkpoints of a run whose items finish faster than that
+pub const INTERVAL: Duration = Duration::from_secs(30);
+
+/// Checkpoint path for a result written to `output_path`
+pub fn path_for(output_path: &str) -> String {
+    format!("{output_path}.checkpoint")
+}
+
+/// Write `value` as JSON to `path`, going through a temporary file so an interrupted write
+/// never leaves a truncated checkpoint behind
+pub fn save<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize: {e}"))?;
+    let tmp_path = format!("{path}.tmp");
+    fs::write(&tmp_path, json).map_err(|e| format!("Failed to write {tmp_path}: {e}"))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {path}: {e}"))
+}
+
+/// A checkpoint rewritten at most once per interval
+///
+/// Every save serializes the whole result, so a run that records thousands of contracts would
+/// spend time quadratic in their number on checkpoints if it saved after each one.
+pub struct Periodic {
+    path: String,
+    interval: Duration,
+    last_save: Instant,
+}
+
+impl Periodic {
+    pub fn new(path: String, interval: Duration) -> Self {
+        Periodic {
+            path,
+            interval,
+            last_save: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Save `value` if the interval has passed since the last save (or the start of the run)
+    pub fn save_if_due<T: Serialize>(&mut self, value: &T) -> Result<(), String> {
+        if self.last_save.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.last_save = Instant::now();
+        save(&self.path, value)
+    }
+}
+
+/// Delete a checkpoint that is no longer needed
+pub fn remove(path: &str) {
+    if let Err(e) = fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::warn!("Failed to remove checkpoint {path}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_replaces_checkpoint() {
+        let path = std::env::temp_dir()
+            .join(format!("checkpoint_{}.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        save(&path, &vec![1, 2]).unwrap();
+        save(&path, &vec![3]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[\n  3\n]");
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+
+        // A zero interval saves every time, a long one waits
+        let mut periodic = Periodic::new(path.clone(), Duration::ZERO);
+        periodic.save_if_due(&vec![4]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[\n  4\n]");
+        let mut periodic = Periodic::new(path.clone(), INTERVAL);
+        periodic.save_if_due(&vec![5]).unwrap();
+        assert_eq!(fs::read_to_string(periodic.path()).unwrap(), "[\n  4\n]");
+
+        remove(&path);
+        assert!(!std::path::Path::new(&path).exists());
+        // Removing a missing checkpoint is not an error
+        remove(&path);
+    }
+}
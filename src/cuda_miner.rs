@@ -1,15 +1,60 @@
 // CUDA-accelerated mining module
+use crate::layout::SlotLayout;
 use log::{info, warn};
 
 #[cfg(feature = "cuda")]
-use crate::storage_miner::{calculate_storage_slot, has_nibble_prefix};
+use crate::account_miner::keccak256;
+#[cfg(feature = "cuda")]
+use crate::search::prefix_matches;
+
+/// Nested mapping keys the kernel supports below the mined key
+#[cfg(feature = "cuda")]
+const MAX_INNER_KEYS: usize = 4;
+
+/// Storage layout passed to the kernel, mirrors `struct SlotLayout` in keccak_cuda.cu
+#[cfg(feature = "cuda")]
+#[repr(C)]
+struct CudaSlotLayout {
+    mapping_slot: [u8; 32],
+    inner_keys: [[u8; 32]; MAX_INNER_KEYS],
+    array_index: u64,
+    num_inner_keys: i32,
+    vyper: i32,
+    key_left_aligned: i32,
+    has_array: i32,
+}
+
+#[cfg(feature = "cuda")]
+impl CudaSlotLayout {
+    /// Convert a layout, or `None` if it nests deeper than the kernel supports
+    fn new(layout: &SlotLayout) -> Option<Self> {
+        use crate::layout::{KeyType, LayoutStyle};
+
+        if layout.inner_keys.len() > MAX_INNER_KEYS {
+            return None;
+        }
+        let mut inner_keys = [[0u8; 32]; MAX_INNER_KEYS];
+        inner_keys[..layout.inner_keys.len()].copy_from_slice(&layout.inner_keys);
+
+        Some(CudaSlotLayout {
+            mapping_slot: *layout.mapping_slot(),
+            inner_keys,
+            array_index: layout.array_index.unwrap_or(0),
+            num_inner_keys: layout.inner_keys.len() as i32,
+            vyper: (layout.style == LayoutStyle::Vyper) as i32,
+            key_left_aligned: (layout.key_type == KeyType::Bytes32) as i32,
+            has_array: layout.array_index.is_some() as i32,
+        })
+    }
+}
 
 #[cfg(feature = "cuda")]
 unsafe extern "C" {
     fn cuda_mine_storage_slot(
         target_prefix: *const u8,
         required_nibbles: i32,
-        base_slot: u64,
+        exact: i32,
+        layout: *const CudaSlotLayout,
         result_address: *mut u8,
         result_storage_key: *mut u8,
         found: *mut bool,
@@ -24,8 +69,17 @@ unsafe extern "C" {
 pub fn mine_with_cuda(
     target_prefix: &[u8; 32],
     required_nibbles: usize,
-    base_slot: u64,
+    exact: bool,
+    layout: &SlotLayout,
 ) -> Option<([u8; 20], [u8; 32])> {
+    let Some(cuda_layout) = CudaSlotLayout::new(layout) else {
+        warn!(
+            "CUDA kernel supports at most {} nested keys below the mined key",
+            MAX_INNER_KEYS
+        );
+        return None;
+    };
+
     let mut result_address = [0u8; 20];
     let mut result_storage_key = [0u8; 32];
     let mut found = false;
@@ -95,7 +149,8 @@ pub fn mine_with_cuda(
             cuda_mine_storage_slot(
                 target_prefix.as_ptr(),
                 required_nibbles as i32,
-                base_slot,
+                exact as i32,
+                &cuda_layout as *const CudaSlotLayout,
                 result_address.as_mut_ptr(),
                 result_storage_key.as_mut_ptr(),
                 &mut found as *mut bool,
@@ -108,8 +163,9 @@ pub fn mine_with_cuda(
 
         if found {
             // Verify the result using CPU to catch any CUDA false positives
-            let cpu_storage_key = calculate_storage_slot(&result_address, base_slot);
-            if !has_nibble_prefix(&cpu_storage_key, target_prefix, required_nibbles) {
+            let cpu_storage_key = layout.storage_key(&result_address);
+            let trie_key = keccak256(&cpu_storage_key);
+            if !prefix_matches(&trie_key, target_prefix, required_nibbles, exact) {
                 warn!(
                     "CUDA returned false positive! Address 0x{} does not match {} nibbles. Continuing search...",
                     hex::encode(&result_address),
@@ -134,7 +190,8 @@ pub fn mine_with_cuda(
 pub fn mine_with_cuda(
     _target_prefix: &[u8; 32],
     _required_nibbles: usize,
-    _base_slot: u64,
+    _exact: bool,
+    _layout: &SlotLayout,
 ) -> Option<([u8; 20], [u8; 32])> {
     panic!("CUDA support not enabled. Build with --features cuda");
 }
@@ -154,6 +211,8 @@ pub fn cuda_available() -> bool {
 
 #[cfg(all(test, feature = "cuda"))]
 mod tests {
+    use super::CudaSlotLayout;
+    use crate::layout::{parse_word, KeyType, LayoutStyle, SlotLayout};
     use crate::storage_miner::calculate_storage_slot;
 
     // Test-only FFI bindings
@@ -164,6 +223,12 @@ mod tests {
             result_storage_key: *mut u8,
         );
 
+        fn cuda_verify_storage_key(
+            layout: *const CudaSlotLayout,
+            test_address: *const u8,
+            result_storage_key: *mut u8,
+        );
+
         fn cuda_debug_prng(
             seed: u64,
             base_slot: u64,
@@ -180,6 +245,15 @@ mod tests {
         result
     }
 
+    fn verify_cuda_storage_key(layout: &SlotLayout, address: &[u8; 20]) -> [u8; 32] {
+        let cuda_layout = CudaSlotLayout::new(layout).expect("layout fits the kernel");
+        let mut result = [0u8; 32];
+        unsafe {
+            cuda_verify_storage_key(&cuda_layout, address.as_ptr(), result.as_mut_ptr());
+        }
+        result
+    }
+
     fn debug_cuda_prng(seed: u64, base_slot: u64) -> ([u8; 20], [u8; 32]) {
         let mut address = [0u8; 20];
         let mut storage_key = [0u8; 32];
@@ -245,4 +319,34 @@ mod tests {
             );
         }
     }
+
+    /// Verify the kernel derives the same storage keys as SlotLayout for nested, array and
+    /// Vyper layouts
+    #[test]
+    fn test_cuda_storage_key_matches_layout() {
+        let test_addr: [u8; 20] = [0x5a; 20];
+        let fixed_key = parse_word("0x1111111111111111111111111111111111111111").unwrap();
+
+        for style in [LayoutStyle::Solidity, LayoutStyle::Vyper] {
+            for key_type in [KeyType::Address, KeyType::Bytes32] {
+                for array_index in [None, Some(0), Some(u64::MAX)] {
+                    let layout = SlotLayout::new(
+                        style,
+                        key_type,
+                        parse_word("9").unwrap(),
+                        vec![fixed_key],
+                        vec![fixed_key, fixed_key],
+                        array_index,
+                    );
+
+                    assert_eq!(
+                        verify_cuda_storage_key(&layout, &test_addr),
+                        layout.storage_key(&test_addr),
+                        "CUDA storage key mismatch for {}",
+                        layout.describe()
+                    );
+                }
+            }
+        }
+    }
 }
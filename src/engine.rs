@@ -0,0 +1,296 @@
+//! # Engine Module
+//!
+//! A long-lived pool of mining threads. The miners submit their prefix searches to it as jobs
+//! instead of spawning and joining a fresh set of threads for every level, auxiliary account
+//! or salt, so thousands of short searches no longer pay thread startup each, and searches
+//! that do not depend on each other (the auxiliaries of one or several contracts) run side by
+//! side.
+//!
+//! A job is a search over an unbounded sequence of attempts, run in batches: a worker claims
+//! the next batch of the highest-priority job (among equals, the one with the fewest workers),
+//! runs it and goes back to the queue. Idle workers therefore steal batches from whatever work
+//! is left instead of sitting on a finished job, and a job ends for everyone with the first hit
+//! or when its handle is cancelled. A search that panics ends its job too: the worker survives,
+//! and the panic is raised again in whoever waits for the job.
+//!
+//! ## Key Functions
+//! - `MiningEngine::shared`: Process-wide engine for a thread count, started on first use
+//! - `MiningEngine::submit`: Queues a search with a priority and returns its handle
+//! - `JobHandle::wait` / `JobHandle::cancel`: Collect a job's hit, or abandon the job
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+
+/// Search run by a job: tests the attempts of one batch and returns the first hit among them
+pub type Search<T> = Box<dyn Fn(Range<u64>) -> Option<T> + Send + Sync>;
+
+/// Pool of mining threads working through a shared queue of search jobs
+pub struct MiningEngine {
+    queue: Arc<Queue>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// Handle to a submitted job; dropping it cancels the job
+pub struct JobHandle<T> {
+    job: Arc<JobState<T>>,
+}
+
+struct Queue {
+    jobs: Mutex<Vec<Arc<dyn Job>>>,
+    available: Condvar,
+    shutdown: AtomicBool,
+    next_sequence: AtomicU64,
+}
+
+struct JobState<T> {
+    search: Search<T>,
+    priority: i64,
+    sequence: u64,
+    batch_size: u64,
+    next_attempt: AtomicU64,
+    workers: AtomicUsize,
+    finished: AtomicBool,
+    hit: Mutex<Option<T>>,
+    /// Payload of a panic raised by the search, handed on to `wait`
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    done: Condvar,
+}
+
+/// The type-erased view of a job the workers schedule
+trait Job: Send + Sync {
+    fn is_finished(&self) -> bool;
+    /// Scheduling rank: higher priority first, then fewer workers, then older jobs
+    fn rank(&self) -> (i64, std::cmp::Reverse<usize>, std::cmp::Reverse<u64>);
+    /// Count a worker in before it leaves the queue lock, so the next pick sees it
+    fn join(&self);
+    /// Run one batch and count the worker out again
+    fn run_batch(&self);
+}
+
+impl MiningEngine {
+    /// Start an engine with `num_threads` workers
+    pub fn new(num_threads: usize) -> Self {
+        let queue = Arc::new(Queue {
+            jobs: Mutex::new(Vec::new()),
+            available: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+            next_sequence: AtomicU64::new(0),
+        });
+
+        let workers = (0..num_threads.max(1))
+            .map(|worker_id| {
+                let queue = Arc::clone(&queue);
+                thread::Builder::new()
+                    .name(format!("miner-{worker_id}"))
+                    .spawn(move || queue.work())
+                    .expect("failed to spawn mining thread")
+            })
+            .collect();
+
+        MiningEngine { queue, workers }
+    }
+
+    /// The engine shared by every search of the process that asks for `num_threads` threads,
+    /// started on first use and kept until exit
+    pub fn shared(num_threads: usize) -> Arc<MiningEngine> {
+        static ENGINES: OnceLock<Mutex<HashMap<usize, Arc<MiningEngine>>>> = OnceLock::new();
+
+        let mut engines = ENGINES.get_or_init(Default::default).lock().unwrap();
+        Arc::clone(
+            engines
+                .entry(num_threads)
+                .or_insert_with(|| Arc::new(MiningEngine::new(num_threads))),
+        )
+    }
+
+    /// Queue a search whose attempts are claimed `batch_size` at a time; higher priorities
+    /// are worked on first
+    pub fn submit<T: Send + 'static>(
+        &self,
+        priority: i64,
+        batch_size: u64,
+        search: Search<T>,
+    ) -> JobHandle<T> {
+        let job = Arc::new(JobState {
+            search,
+            priority,
+            sequence: self.queue.next_sequence.fetch_add(1, Ordering::Relaxed),
+            batch_size: batch_size.max(1),
+            next_attempt: AtomicU64::new(0),
+            workers: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            hit: Mutex::new(None),
+            panic: Mutex::new(None),
+            done: Condvar::new(),
+        });
+
+        self.queue.jobs.lock().unwrap().push(job.clone());
+        self.queue.available.notify_all();
+        JobHandle { job }
+    }
+}
+
+impl Drop for MiningEngine {
+    fn drop(&mut self) {
+        self.queue.shutdown.store(true, Ordering::SeqCst);
+        // Take the lock so no worker misses the wakeup between its check and its wait
+        drop(self.queue.jobs.lock().unwrap());
+        self.queue.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Queue {
+    /// Worker loop: run batches of the best-ranked job until the engine shuts down
+    fn work(&self) {
+        while let Some(job) = self.next_job() {
+            job.run_batch();
+        }
+    }
+
+    /// Wait for the best-ranked unfinished job, or `None` on shutdown
+    fn next_job(&self) -> Option<Arc<dyn Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            if self.shutdown.load(Ordering::SeqCst) {
+                return None;
+            }
+            jobs.retain(|job| !job.is_finished());
+            if let Some(job) = jobs.iter().max_by_key(|job| job.rank()) {
+                job.join();
+                return Some(Arc::clone(job));
+            }
+            jobs = self.available.wait(jobs).unwrap();
+        }
+    }
+}
+
+impl<T: Send> Job for JobState<T> {
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    fn rank(&self) -> (i64, std::cmp::Reverse<usize>, std::cmp::Reverse<u64>) {
+        (
+            self.priority,
+            std::cmp::Reverse(self.workers.load(Ordering::Relaxed)),
+            std::cmp::Reverse(self.sequence),
+        )
+    }
+
+    fn join(&self) {
+        self.workers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn run_batch(&self) {
+        if !self.is_finished() {
+            let start = self
+                .next_attempt
+                .fetch_add(self.batch_size, Ordering::Relaxed);
+            let batch = start..start.saturating_add(self.batch_size);
+            match panic::catch_unwind(AssertUnwindSafe(|| (self.search)(batch))) {
+                Ok(None) => {}
+                Ok(hit) => self.finish(hit),
+                Err(payload) => {
+                    self.panic.lock().unwrap().get_or_insert(payload);
+                    self.finish(None);
+                }
+            }
+        }
+        self.workers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<T> JobState<T> {
+    /// End the job with `hit` unless another worker (or a cancel) ended it first
+    fn finish(&self, hit: Option<T>) {
+        let mut slot = self.hit.lock().unwrap();
+        if !self.finished.load(Ordering::Acquire) {
+            *slot = hit;
+            self.finished.store(true, Ordering::Release);
+            self.done.notify_all();
+        }
+    }
+}
+
+impl<T> JobHandle<T> {
+    /// Block until the job finds a hit and take it, or return `None` if it was cancelled
+    ///
+    /// Panics with the search's own panic if the search panicked.
+    pub fn wait(&self) -> Option<T> {
+        let mut hit = self.job.hit.lock().unwrap();
+        while !self.job.finished.load(Ordering::Acquire) {
+            hit = self.job.done.wait(hit).unwrap();
+        }
+        if let Some(payload) = self.job.panic.lock().unwrap().take() {
+            drop(hit);
+            panic::resume_unwind(payload);
+        }
+        hit.take()
+    }
+
+    /// Stop the job; workers leave it after their current batch
+    pub fn cancel(&self) {
+        self.job.finish(None);
+    }
+
+    /// Attempts claimed so far, including batches still running
+    pub fn attempts(&self) -> u64 {
+        self.job.next_attempt.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for JobHandle<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_find_hits_and_cancel() {
+        let engine = MiningEngine::new(3);
+
+        // The higher-priority job never finishes, so the other one only runs once it is
+        // cancelled
+        let never = engine.submit(1, 100, Box::new(|_: Range<u64>| None::<u64>));
+        // Attempt 12345 is the only hit, so the workers have to share the batches
+        let exact = engine.submit(
+            0,
+            100,
+            Box::new(|batch: Range<u64>| batch.into_iter().find(|&i| i == 12_345)),
+        );
+
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(exact.attempts(), 0);
+        never.cancel();
+        assert_eq!(never.wait(), None);
+        assert_eq!(exact.wait(), Some(12_345));
+    }
+
+    #[test]
+    fn test_panicking_search_fails_waiter() {
+        let engine = MiningEngine::new(1);
+        let broken = engine.submit(
+            0,
+            10,
+            Box::new(|batch: Range<u64>| -> Option<u64> { panic!("bad batch {batch:?}") }),
+        );
+        let waited = panic::catch_unwind(AssertUnwindSafe(|| broken.wait()));
+        assert!(waited.is_err());
+
+        // The only worker survived the panic and still runs new jobs
+        let next = engine.submit(0, 10, Box::new(|batch: Range<u64>| Some(batch.start)));
+        assert_eq!(next.wait(), Some(0));
+    }
+}
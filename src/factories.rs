@@ -0,0 +1,469 @@
+//! # Factories Module
+//!
+//! Deployment payloads for the CREATE2 factories mined contracts are usually deployed
+//! through. Nick's deterministic deployer and the Safe singleton factory take `salt ||
+//! initcode` as raw calldata; CreateX takes an ABI-encoded `deployCreate2(bytes32,bytes)`
+//! call and guards the salt before using it, so CreateX contracts are mined on the guarded
+//! salt (`SaltGuard`). Each factory's address scheme is evaluated on the payload itself, so a
+//! payload is only handed out when it deploys the mined address.
+//!
+//! ## Key Functions
+//! - `Factory::calldata`: Payload deploying an init code with a salt through the factory
+//! - `Factory::deployed_address`: Address the factory derives from a payload and its caller
+//! - `SaltGuard::apply`: The salt a deployer passes to CREATE2, used when mining
+//! - `createx_guarded_salt`: The salt CreateX passes to CREATE2 for a given salt
+//! - `deployments`: Checked factory calls for every contract of a CREATE2 result
+
+use crate::account_miner::{Create2MiningResult, calculate_create2_address, keccak256};
+use crate::bytecode::selector;
+use crate::parse_hex_array;
+use serde::Serialize;
+
+/// Nick's deterministic deployment proxy
+pub const NICK_FACTORY: [u8; 20] = [
+    0x4e, 0x59, 0xb4, 0x48, 0x47, 0xb3, 0x79, 0x57, 0x85, 0x88, 0x92, 0x0c, 0xa7, 0x8f, 0xbf, 0x26,
+    0xc0, 0xb4, 0x95, 0x6c,
+];
+
+/// CreateX, at the same address on every chain it is deployed to
+pub const CREATEX_FACTORY: [u8; 20] = [
+    0xba, 0x5e, 0xd0, 0x99, 0x63, 0x3d, 0x3b, 0x31, 0x3e, 0x4d, 0x5f, 0x7b, 0xdc, 0x13, 0x05, 0xd3,
+    0xc2, 0x8b, 0xa5, 0xed,
+];
+
+/// Safe singleton factory
+pub const SAFE_SINGLETON_FACTORY: [u8; 20] = [
+    0x91, 0x4d, 0x7f, 0xec, 0x6a, 0xac, 0x8c, 0xd5, 0x42, 0xe7, 0x2b, 0xca, 0x78, 0xb3, 0x06, 0x50,
+    0xd4, 0x56, 0x43, 0xd7,
+];
+
+/// A CREATE2 factory contract
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Factory {
+    /// Nick's deterministic deployer (calldata: salt || initcode)
+    Nick,
+    /// CreateX `deployCreate2(bytes32,bytes)`, which guards the salt
+    Createx,
+    /// Safe singleton factory (calldata: salt || initcode)
+    Safe,
+}
+
+impl std::fmt::Display for Factory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Factory::Nick => "Nick's deployer",
+            Factory::Createx => "CreateX",
+            Factory::Safe => "Safe singleton factory",
+        })
+    }
+}
+
+impl Factory {
+    pub fn address(self) -> [u8; 20] {
+        match self {
+            Factory::Nick => NICK_FACTORY,
+            Factory::Createx => CREATEX_FACTORY,
+            Factory::Safe => SAFE_SINGLETON_FACTORY,
+        }
+    }
+
+    /// The factory deployed at `address`, if it is a known one
+    pub fn at(address: &[u8; 20]) -> Option<Self> {
+        [Factory::Nick, Factory::Createx, Factory::Safe]
+            .into_iter()
+            .find(|factory| factory.address() == *address)
+    }
+
+    /// Calldata that makes the factory deploy `init_code` with `salt`
+    pub fn calldata(self, salt: &[u8; 32], init_code: &[u8]) -> Vec<u8> {
+        match self {
+            Factory::Nick | Factory::Safe => [salt.as_slice(), init_code].concat(),
+            Factory::Createx => {
+                let mut data = selector("deployCreate2(bytes32,bytes)").to_vec();
+                data.extend_from_slice(salt);
+                data.extend_from_slice(&abi_word(0x40));
+                data.extend_from_slice(&abi_word(init_code.len() as u64));
+                data.extend_from_slice(init_code);
+                data.resize(data.len() + (32 - init_code.len() % 32) % 32, 0);
+                data
+            }
+        }
+    }
+
+    /// Address the factory deploys to when `caller` sends it `calldata` on `chain_id`,
+    /// following the factory's own decoding of the payload
+    pub fn deployed_address(
+        self,
+        calldata: &[u8],
+        caller: Option<&[u8; 20]>,
+        chain_id: Option<u64>,
+    ) -> Result<[u8; 20], String> {
+        let (salt, init_code) = match self {
+            Factory::Nick | Factory::Safe => {
+                if calldata.len() < 32 {
+                    return Err("Calldata is shorter than a salt".to_string());
+                }
+                let salt: [u8; 32] = calldata[..32].try_into().unwrap();
+                (salt, &calldata[32..])
+            }
+            Factory::Createx => {
+                let (salt, init_code) = decode_deploy_create2(calldata)?;
+                (createx_guarded_salt(&salt, caller, chain_id)?, init_code)
+            }
+        };
+        Ok(calculate_create2_address(
+            &self.address(),
+            &salt,
+            &keccak256(init_code),
+        ))
+    }
+}
+
+/// What a deployer does to the salt it is called with before CREATE2 sees it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SaltGuard {
+    /// The salt is used as given
+    #[default]
+    Plain,
+    /// CreateX's `_guard` for salts sent by `caller` on `chain_id`
+    Createx {
+        caller: Option<[u8; 20]>,
+        chain_id: Option<u64>,
+    },
+}
+
+impl SaltGuard {
+    /// The guard of `deployer`: CreateX's if it is CreateX, none for any other deployer
+    pub fn for_deployer(
+        deployer: &[u8; 20],
+        caller: Option<[u8; 20]>,
+        chain_id: Option<u64>,
+    ) -> Self {
+        match Factory::at(deployer) {
+            Some(Factory::Createx) => SaltGuard::Createx { caller, chain_id },
+            _ => SaltGuard::Plain,
+        }
+    }
+
+    /// The salt CREATE2 sees when the deployer is called with `salt`
+    pub fn apply(&self, salt: &[u8; 32]) -> Result<[u8; 32], String> {
+        match self {
+            SaltGuard::Plain => Ok(*salt),
+            SaltGuard::Createx { caller, chain_id } => {
+                createx_guarded_salt(salt, caller.as_ref(), *chain_id)
+            }
+        }
+    }
+
+    /// Caller the guard binds salts to, if any
+    pub fn caller(&self) -> Option<[u8; 20]> {
+        match self {
+            SaltGuard::Plain => None,
+            SaltGuard::Createx { caller, .. } => *caller,
+        }
+    }
+
+    /// Chain id the guard binds salts to, if any
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            SaltGuard::Plain => None,
+            SaltGuard::Createx { chain_id, .. } => *chain_id,
+        }
+    }
+}
+
+/// Factory calls deploying every contract of a CREATE2 result
+#[derive(Serialize)]
+pub struct DeploymentPlan {
+    pub factory: Factory,
+    pub factory_address: String,
+    pub init_code_hash: String,
+    pub deployments: Vec<Deployment>,
+}
+
+/// The call that deploys one mined contract
+#[derive(Serialize)]
+pub struct Deployment {
+    pub salt: String,
+    pub contract_address: String,
+    pub to: String,
+    #[serde(serialize_with = "serialize_hex")]
+    pub data: Vec<u8>,
+}
+
+/// Build the factory call of every contract in `result` and check that the factory derives
+/// the mined address from it; a payload deploying anywhere else is an error
+pub fn deployments(
+    result: &Create2MiningResult,
+    init_code: &[u8],
+    factory: Factory,
+    caller: Option<&[u8; 20]>,
+    chain_id: Option<u64>,
+) -> Result<DeploymentPlan, String> {
+    let init_code_hash = keccak256(init_code);
+    if parse_hex_array::<32>(&result.init_code_hash)? != init_code_hash {
+        return Err(format!(
+            "Init code hash 0x{} does not match the result's {}",
+            hex::encode(init_code_hash),
+            result.init_code_hash
+        ));
+    }
+    let deployer = parse_hex_array::<20>(&result.deployer)?;
+    let guard = result.salt_guard()?;
+    if deployer != factory.address() {
+        return Err(format!(
+            "Contracts were mined for deployer {}, not {factory} at 0x{}",
+            result.deployer,
+            hex::encode(factory.address())
+        ));
+    }
+
+    let mut deployments = Vec::with_capacity(result.contracts.len());
+    for contract in &result.contracts {
+        let salt = parse_hex_array::<32>(&contract.salt)?;
+        let contract_address = parse_hex_array::<20>(&contract.contract_address)?;
+        let predicted = calculate_create2_address(&deployer, &guard.apply(&salt)?, &init_code_hash);
+        if predicted != contract_address {
+            return Err(format!(
+                "Salt {} gives 0x{}, not the recorded {}",
+                contract.salt,
+                hex::encode(predicted),
+                contract.contract_address
+            ));
+        }
+
+        let data = factory.calldata(&salt, init_code);
+        let deployed = factory.deployed_address(&data, caller, chain_id)?;
+        if deployed != predicted {
+            let hint = match factory {
+                Factory::Createx => {
+                    " (CreateX binds the salt to the caller and chain id it was mined for)"
+                }
+                _ => "",
+            };
+            return Err(format!(
+                "{factory} deploys salt {} to 0x{}, not the mined 0x{}{hint}",
+                contract.salt,
+                hex::encode(deployed),
+                hex::encode(predicted)
+            ));
+        }
+
+        deployments.push(Deployment {
+            salt: contract.salt.clone(),
+            contract_address: format!("0x{}", hex::encode(contract_address)),
+            to: format!("0x{}", hex::encode(factory.address())),
+            data,
+        });
+    }
+
+    Ok(DeploymentPlan {
+        factory,
+        factory_address: format!("0x{}", hex::encode(factory.address())),
+        init_code_hash: format!("0x{}", hex::encode(init_code_hash)),
+        deployments,
+    })
+}
+
+/// The salt CreateX's `_guard` derives: a salt starting with the caller is bound to it (and,
+/// with 0x01 in byte 20, to the chain), a zero-address salt with 0x01 is bound to the chain,
+/// and any other salt is hashed. Either way CREATE2 never sees the salt as given.
+pub fn createx_guarded_salt(
+    salt: &[u8; 32],
+    caller: Option<&[u8; 20]>,
+    chain_id: Option<u64>,
+) -> Result<[u8; 32], String> {
+    let chain = || {
+        chain_id
+            .map(abi_word)
+            .ok_or("CreateX binds this salt to the chain: --chain-id is needed")
+    };
+    let sender = &salt[..20];
+    let from_caller = caller.is_some_and(|caller| sender == caller.as_slice());
+    let from_zero = sender == [0u8; 20];
+
+    match (from_caller, from_zero, salt[20]) {
+        (true, _, 0x01) => {
+            let mut caller_word = [0u8; 32];
+            caller_word[12..].copy_from_slice(sender);
+            Ok(keccak256(
+                &[caller_word.as_slice(), &chain()?, salt].concat(),
+            ))
+        }
+        (true, _, 0x00) => {
+            let mut caller_word = [0u8; 32];
+            caller_word[12..].copy_from_slice(sender);
+            Ok(keccak256(&[caller_word.as_slice(), salt].concat()))
+        }
+        (true, _, _) | (false, true, 0x02..) => Err(format!(
+            "CreateX rejects salt 0x{}: byte 20 must be 0x00 or 0x01",
+            hex::encode(salt)
+        )),
+        (false, true, 0x01) => Ok(keccak256(&[chain()?.as_slice(), salt].concat())),
+        _ => Ok(keccak256(salt)),
+    }
+}
+
+/// Decode the salt and init code of a `deployCreate2(bytes32,bytes)` call
+fn decode_deploy_create2(calldata: &[u8]) -> Result<([u8; 32], &[u8]), String> {
+    let invalid = || "Malformed deployCreate2 calldata".to_string();
+    if calldata.get(..4) != Some(selector("deployCreate2(bytes32,bytes)").as_slice()) {
+        return Err("Calldata is not a deployCreate2(bytes32,bytes) call".to_string());
+    }
+
+    let word = |offset: usize| -> Result<usize, String> {
+        let word = calldata.get(offset..offset + 32).ok_or_else(invalid)?;
+        if word[..24].iter().any(|&b| b != 0) {
+            return Err(invalid());
+        }
+        Ok(u64::from_be_bytes(word[24..].try_into().unwrap()) as usize)
+    };
+    let salt: [u8; 32] = calldata.get(4..36).ok_or_else(invalid)?.try_into().unwrap();
+    let start = 4 + word(36)?;
+    let len = word(start)?;
+    let init_code = calldata
+        .get(start + 32..start + 32 + len)
+        .ok_or_else(invalid)?;
+    Ok((salt, init_code))
+}
+
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+}
+
+/// A u64 as a 32-byte big-endian ABI word
+fn abi_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_deploy_to_the_factory_schemes() {
+        let init_code = [0x60, 0x00, 0x60, 0x00, 0xf3];
+        let init_code_hash = keccak256(&init_code);
+        let salt = [0x42; 32];
+
+        // Nick's deployer and the Safe factory use the salt as given
+        for factory in [Factory::Nick, Factory::Safe] {
+            let calldata = factory.calldata(&salt, &init_code);
+            assert_eq!(
+                factory.deployed_address(&calldata, None, None).unwrap(),
+                calculate_create2_address(&factory.address(), &salt, &init_code_hash)
+            );
+        }
+
+        // CreateX hashes a salt that is neither the caller's nor the zero address's
+        let calldata = Factory::Createx.calldata(&salt, &init_code);
+        assert_eq!(calldata.len(), 4 + 32 * 4);
+        let deployed = Factory::Createx
+            .deployed_address(&calldata, None, None)
+            .unwrap();
+        assert_eq!(
+            deployed,
+            calculate_create2_address(&CREATEX_FACTORY, &keccak256(&salt), &init_code_hash)
+        );
+        assert_ne!(
+            deployed,
+            calculate_create2_address(&CREATEX_FACTORY, &salt, &init_code_hash)
+        );
+
+        // A caller-bound salt with cross-chain protection needs the chain id
+        let caller = [0x11; 20];
+        let mut bound = [0u8; 32];
+        bound[..20].copy_from_slice(&caller);
+        bound[20] = 0x01;
+        assert!(createx_guarded_salt(&bound, Some(&caller), None).is_err());
+        assert!(createx_guarded_salt(&bound, Some(&caller), Some(1)).is_ok());
+        bound[20] = 0x02;
+        assert!(createx_guarded_salt(&bound, Some(&caller), Some(1)).is_err());
+
+        assert_eq!(Factory::at(&NICK_FACTORY), Some(Factory::Nick));
+        assert_eq!(Factory::at(&[0u8; 20]), None);
+    }
+
+    /// Contracts mined on CreateX's guarded salt deploy to their mined address, for hashed
+    /// as well as caller- and chain-bound salts
+    #[test]
+    fn test_createx_deployment_verifies() {
+        use crate::account_miner::{ContractWithAuxiliaries, verify_results};
+
+        let init_code = [0x60, 0x00, 0x60, 0x00, 0xf3];
+        let init_code_hash = keccak256(&init_code);
+        let caller = [0x11; 20];
+        let mut bound = [0u8; 32];
+        bound[..20].copy_from_slice(&caller);
+        bound[20] = 0x01;
+
+        for (salt, guard) in [
+            (
+                [0x42; 32],
+                SaltGuard::for_deployer(&CREATEX_FACTORY, None, None),
+            ),
+            (
+                bound,
+                SaltGuard::for_deployer(&CREATEX_FACTORY, Some(caller), Some(1)),
+            ),
+        ] {
+            let guarded = guard.apply(&salt).unwrap();
+            let address = calculate_create2_address(&CREATEX_FACTORY, &guarded, &init_code_hash);
+            let result = Create2MiningResult {
+                deployer: format!("0x{}", hex::encode(CREATEX_FACTORY)),
+                init_code_hash: format!("0x{}", hex::encode(init_code_hash)),
+                target_depth: 0,
+                num_contracts: 1,
+                salt_prefix: "0x".to_string(),
+                salt_start: 0,
+                caller: guard
+                    .caller()
+                    .map(|caller| format!("0x{}", hex::encode(caller))),
+                chain_id: guard.chain_id(),
+                contract_nibbles: 0,
+                contract_target: None,
+                exact: false,
+                total_time: 0.0,
+                contracts: vec![ContractWithAuxiliaries {
+                    salt: format!("0x{}", hex::encode(salt)),
+                    contract_address: format!("0x{}", hex::encode(address)),
+                    auxiliary_accounts: Vec::new(),
+                    auxiliary_keys: Vec::new(),
+                    auxiliary_shared_nibbles: Vec::new(),
+                    trie: None,
+                }],
+            };
+            verify_results(&result).unwrap();
+
+            let plan = deployments(
+                &result,
+                &init_code,
+                Factory::Createx,
+                guard.caller().as_ref(),
+                guard.chain_id(),
+            )
+            .unwrap();
+            assert_eq!(
+                plan.deployments[0].contract_address,
+                format!("0x{}", hex::encode(address))
+            );
+
+            // A bound salt deploys elsewhere when another account sends it
+            if guard.caller().is_some() {
+                assert!(
+                    deployments(
+                        &result,
+                        &init_code,
+                        Factory::Createx,
+                        Some(&[0x22; 20]),
+                        Some(1)
+                    )
+                    .is_err()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! # Forest Module
+//!
+//! Mines several deep storage branches into one contract instead of a single chain. A forest
+//! puts independent chains under different first nibbles of the hashed storage keys; fan-out
+//! mode additionally mines a sibling for every empty child of the branch nodes along each
+//! chain, so every branch node on the path to the deepest keys is saturated with 16 children.
+//!
+//! ## Key Functions
+//! - `mine_forest`: Mines the chains and (optionally) the fan-out siblings
+//! - `write_results` / `verify_results`: JSON output and re-verification, including the
+//!   aggregate shape of the resulting storage trie
+
+use crate::layout::{LayoutInfo, SlotLayout};
+use crate::mpt::{Trie, TrieShape};
+use crate::rlp;
+use crate::search;
+use crate::storage_miner::{self, MinedStorageSlot, StorageSlot};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Instant;
+
+/// Mined chains plus the siblings saturating their branch nodes
+pub struct Forest {
+    pub branches: Vec<Vec<StorageSlot>>,
+    pub siblings: Vec<StorageSlot>,
+}
+
+/// Result structure for forest mining
+#[derive(Serialize, Deserialize)]
+pub struct ForestMiningResult {
+    /// Levels per branch
+    pub depth: usize,
+    pub total_time: f64,
+    pub fan_out: bool,
+    #[serde(default)]
+    pub layout: LayoutInfo,
+    pub branches: Vec<Vec<MinedStorageSlot>>,
+    /// Keys filling the empty children of the branch nodes along each chain; `depth` is the
+    /// nibble position they fill and `shared_nibbles` counts against the chain's deepest key
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub siblings: Vec<MinedStorageSlot>,
+    /// Shape of the storage trie holding every mined key
+    pub trie: TrieShape,
+    /// Whether every chain level shares exactly its required nibbles with the previous one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+}
+
+impl Forest {
+    /// Every mined slot, with the deepest key of the last chain last (the slot `attack()`
+    /// of the generated contract writes)
+    pub fn all_slots(&self) -> Vec<StorageSlot> {
+        self.siblings
+            .iter()
+            .chain(self.branches.iter().flatten())
+            .cloned()
+            .collect()
+    }
+
+    /// Storage trie holding every hashed mined key, each set to 1 like the generated contracts do
+    pub fn trie(&self) -> Trie {
+        let mut trie = Trie::new();
+        for slot in self.all_slots() {
+            trie.insert(&slot.trie_key(), rlp::encode_u64(1));
+        }
+        trie
+    }
+}
+
+impl ForestMiningResult {
+    pub fn from_forest(
+        forest: &Forest,
+        target_depth: usize,
+        total_time: f64,
+        fan_out: bool,
+        exact: bool,
+        layout: &SlotLayout,
+    ) -> Self {
+        let siblings = storage_miner::mined_slots(&forest.siblings, None)
+            .into_iter()
+            .zip(&forest.siblings)
+            .map(|(mut entry, sibling)| {
+                let deepest = deepest_key_above(forest, sibling);
+                entry.shared_nibbles = search::count_shared_nibbles(&deepest, &sibling.trie_key());
+                entry
+            })
+            .collect();
+
+        ForestMiningResult {
+            depth: target_depth,
+            total_time,
+            fan_out,
+            layout: layout.info(),
+            branches: forest
+                .branches
+                .iter()
+                .map(|branch| storage_miner::mined_slots(branch, None))
+                .collect(),
+            siblings,
+            trie: forest.trie().shape(),
+            exact,
+        }
+    }
+
+    /// Rebuild the forest from a deserialized result
+    pub fn to_forest(&self) -> Result<Forest, String> {
+        let to_slots = |entries: &[MinedStorageSlot]| -> Result<Vec<StorageSlot>, String> {
+            entries.iter().map(MinedStorageSlot::to_slot).collect()
+        };
+
+        Ok(Forest {
+            branches: self
+                .branches
+                .iter()
+                .map(|branch| to_slots(branch))
+                .collect::<Result<_, _>>()?,
+            siblings: to_slots(&self.siblings)?,
+        })
+    }
+}
+
+/// Mine `num_branches` chains of `target_depth` levels, and with `fan_out` the siblings that
+/// saturate every branch node along them
+///
+/// With more than one branch, chain `i` is rooted under first nibble `i * 16 / num_branches`.
+/// `exact` applies to the chains; a sibling is the only key below its nibble anyway.
+pub fn mine_forest(
+    target_depth: usize,
+    num_branches: usize,
+    fan_out: bool,
+    exact: bool,
+    num_threads: usize,
+    use_cuda: bool,
+    layout: &SlotLayout,
+) -> Forest {
+    let mut forest = Forest {
+        branches: Vec::new(),
+        siblings: Vec::new(),
+    };
+
+    for index in 0..num_branches {
+        info!("");
+        info!("═══ Branch {}/{} ═══", index + 1, num_branches);
+
+        // A single branch stays unconstrained, like `storage` without --branches
+        let mut first_level = Vec::new();
+        if num_branches > 1 {
+            let nibble = (index * 16 / num_branches) as u8;
+            let level_start = Instant::now();
+            let Some(address) = mine_slot(&[nibble << 4; 32], 1, num_threads, use_cuda, layout)
+            else {
+                info!("Failed to find a first level under nibble {nibble:x} - stopping");
+                break;
+            };
+            first_level.push(StorageSlot {
+                address,
+                storage_key: layout.storage_key(&address),
+                depth: 0,
+                time_taken: level_start.elapsed().as_secs_f64(),
+            });
+            info!("Branch rooted under first nibble {nibble:x}");
+        }
+
+        let branch = storage_miner::mine_deep_branch(
+            target_depth,
+            num_threads,
+            use_cuda,
+            layout,
+            None,
+            exact,
+            first_level,
+            None,
+        );
+        forest.branches.push(branch);
+    }
+
+    if fan_out {
+        for index in 0..forest.branches.len() {
+            info!("");
+            info!("═══ Fan-out siblings for branch {} ═══", index + 1);
+            mine_siblings(&mut forest, index, num_threads, use_cuda, layout);
+        }
+    }
+
+    forest
+}
+
+/// Mine a key for every empty child of the branch nodes on the path to the deepest key of
+/// `forest.branches[index]`
+fn mine_siblings(
+    forest: &mut Forest,
+    index: usize,
+    num_threads: usize,
+    use_cuda: bool,
+    layout: &SlotLayout,
+) {
+    let Some(deepest) = forest.branches[index].last().map(StorageSlot::trie_key) else {
+        return;
+    };
+
+    for position in 0..forest.branches[index].len() {
+        // Children already present below the first `position` nibbles of the deepest key
+        let mut occupied = [false; 16];
+        for slot in forest.all_slots() {
+            let trie_key = slot.trie_key();
+            if search::has_nibble_prefix(&trie_key, &deepest, position) {
+                occupied[nibble_at(&trie_key, position) as usize] = true;
+            }
+        }
+
+        let missing: Vec<u8> = (0..16u8).filter(|&n| !occupied[n as usize]).collect();
+        info!(
+            "Nibble position {position}: mining {} siblings ({} nibbles each)",
+            missing.len(),
+            position + 1
+        );
+
+        for nibble in missing {
+            let mut target = deepest;
+            set_nibble(&mut target, position, nibble);
+
+            let level_start = Instant::now();
+            let Some(address) = mine_slot(&target, position + 1, num_threads, use_cuda, layout)
+            else {
+                info!("Failed to find a sibling at nibble position {position} - skipping");
+                continue;
+            };
+            forest.siblings.push(StorageSlot {
+                address,
+                storage_key: layout.storage_key(&address),
+                depth: position,
+                time_taken: level_start.elapsed().as_secs_f64(),
+            });
+        }
+    }
+}
+
+/// Mine a key whose hashed storage key matches the first `nibbles` nibbles of `target`
+fn mine_slot(
+    target: &[u8; 32],
+    nibbles: usize,
+    num_threads: usize,
+    use_cuda: bool,
+    layout: &SlotLayout,
+) -> Option<[u8; 20]> {
+    // Same threshold as `mine_deep_branch`: CUDA only pays off from 8 nibbles on
+    storage_miner::mine_address_for_prefix(
+        target,
+        nibbles,
+        false,
+        num_threads,
+        use_cuda && nibbles >= 8,
+        layout,
+    )
+}
+
+/// Hashed deepest key of the chain whose path the sibling fills
+fn deepest_key_above(forest: &Forest, sibling: &StorageSlot) -> [u8; 32] {
+    let sibling_key = sibling.trie_key();
+    forest
+        .branches
+        .iter()
+        .filter_map(|branch| branch.last())
+        .map(StorageSlot::trie_key)
+        .max_by_key(|key| search::count_shared_nibbles(key, &sibling_key))
+        .unwrap_or_default()
+}
+
+fn nibble_at(key: &[u8; 32], position: usize) -> u8 {
+    let byte = key[position / 2];
+    if position.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0F
+    }
+}
+
+fn set_nibble(key: &mut [u8; 32], position: usize, nibble: u8) {
+    let byte = &mut key[position / 2];
+    *byte = if position.is_multiple_of(2) {
+        (*byte & 0x0F) | (nibble << 4)
+    } else {
+        (*byte & 0xF0) | nibble
+    };
+}
+
+/// Log the forest and the aggregate trie shape
+pub fn print_results(forest: &Forest, elapsed_seconds: f64) {
+    info!("");
+    info!("╔════════════════════════════════════════════════════════════════════════╗");
+    info!("║                        FOREST MINING RESULTS                           ║");
+    info!("╚════════════════════════════════════════════════════════════════════════╝");
+    info!("");
+
+    for (i, branch) in forest.branches.iter().enumerate() {
+        if let (Some(first), Some(last)) = (branch.first(), branch.last()) {
+            info!(
+                "Branch {}: {} levels, 0x{}... -> 0x{}...",
+                i + 1,
+                branch.len(),
+                hex::encode(&first.trie_key()[..4]),
+                hex::encode(&last.trie_key()[..4])
+            );
+        }
+    }
+    if !forest.siblings.is_empty() {
+        info!("Fan-out siblings: {}", forest.siblings.len());
+    }
+
+    log_shape(&forest.trie().shape());
+    info!("Total time taken: {elapsed_seconds:.2} seconds");
+    info!("");
+}
+
+fn log_shape(shape: &TrieShape) {
+    info!("Storage root: {}", shape.root);
+    info!(
+        "Trie shape: {} leaves, {} branch nodes ({} full), {} extension nodes, max depth {} nodes",
+        shape.leaves,
+        shape.branch_nodes,
+        shape.full_branch_nodes,
+        shape.extension_nodes,
+        shape.max_depth
+    );
+    for (depth, count) in shape.leaf_depths.iter().enumerate() {
+        if *count > 0 {
+            info!("  {count} leaves at depth {depth}");
+        }
+    }
+}
+
+/// Write the forest to a JSON file
+pub fn write_results(
+    forest: &Forest,
+    target_depth: usize,
+    elapsed_seconds: f64,
+    fan_out: bool,
+    exact: bool,
+    layout: &SlotLayout,
+    output_path: &str,
+) {
+    let result = ForestMiningResult::from_forest(
+        forest,
+        target_depth,
+        elapsed_seconds,
+        fan_out,
+        exact,
+        layout,
+    );
+
+    match serde_json::to_string_pretty(&result) {
+        Ok(json) => {
+            if let Err(e) = fs::write(output_path, json) {
+                log::error!("Failed to write JSON: {e}");
+            } else {
+                info!("Results saved to: {output_path}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to serialize to JSON: {e}");
+        }
+    }
+}
+
+/// Load a forest mining result from a JSON file
+pub fn load_results(path: &str) -> Result<ForestMiningResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid forest result JSON: {e}"))
+}
+
+/// Check every chain like a single storage branch, that chains start under distinct first
+/// nibbles, that siblings follow from their keys, and that the recorded trie shape matches
+pub fn verify_results(result: &ForestMiningResult) -> Result<(), String> {
+    let forest = result.to_forest()?;
+    let layout = result.layout.to_layout()?;
+
+    let mut first_nibbles = Vec::new();
+    for (i, branch) in forest.branches.iter().enumerate() {
+        storage_miner::verify_levels(branch, &layout, None, result.exact)
+            .map_err(|e| format!("Branch {}: {e}", i + 1))?;
+
+        if forest.branches.len() > 1
+            && let Some(first) = branch.first()
+        {
+            let nibble = nibble_at(&first.trie_key(), 0);
+            if first_nibbles.contains(&nibble) {
+                return Err(format!(
+                    "Branch {}: shares first nibble {nibble:x} with another branch",
+                    i + 1
+                ));
+            }
+            first_nibbles.push(nibble);
+        }
+    }
+
+    for sibling in &forest.siblings {
+        if layout.storage_key(&sibling.address) != sibling.storage_key {
+            return Err(format!(
+                "Sibling 0x{}: storage key does not match {}",
+                hex::encode(sibling.address),
+                layout.describe()
+            ));
+        }
+        if !search::has_nibble_prefix(
+            &sibling.trie_key(),
+            &deepest_key_above(&forest, sibling),
+            sibling.depth,
+        ) {
+            return Err(format!(
+                "Sibling 0x{}: storage key does not share {} nibbles with any branch",
+                hex::encode(sibling.address),
+                sibling.depth
+            ));
+        }
+    }
+
+    let shape = forest.trie().shape();
+    if shape != result.trie {
+        return Err(format!(
+            "Recorded trie shape (root {}) does not match the mined keys (root {})",
+            result.trie.root, shape.root
+        ));
+    }
+    log_shape(&shape);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nibble_helpers() {
+        let mut key = [0u8; 32];
+        set_nibble(&mut key, 0, 0xa);
+        set_nibble(&mut key, 3, 0x5);
+        assert_eq!(key[..2], [0xa0, 0x05]);
+        assert_eq!(nibble_at(&key, 0), 0xa);
+        assert_eq!(nibble_at(&key, 1), 0);
+        assert_eq!(nibble_at(&key, 3), 5);
+    }
+
+    /// A two-level fan-out saturates the root and the branch below it
+    #[test]
+    fn test_fan_out_saturates_path() {
+        let layout = SlotLayout::default();
+        let forest = mine_forest(2, 1, true, false, 2, false, &layout);
+
+        let shape = forest.trie().shape();
+        assert_eq!(forest.branches[0].len(), 2);
+        assert!(shape.full_branch_nodes >= 2, "{shape:?}");
+
+        let result = ForestMiningResult::from_forest(&forest, 2, 0.0, true, false, &layout);
+        verify_results(&result).unwrap();
+    }
+}
@@ -0,0 +1,449 @@
+//! # Genesis Module
+//!
+//! Exports mined accounts as the `alloc` section of a geth/reth genesis file, so a devnet
+//! boots with the deep account branches already in its state. Auxiliary accounts (and mined
+//! CREATE deployers) get a balance, since EIP-161 treats balance-less accounts as empty and
+//! leaves them out of the trie. CREATE2 contracts are either left for the factory to deploy or
+//! pre-deployed with the runtime code and storage their initcode would have produced.
+//!
+//! A mined storage branch can be placed the same way, as a single contract whose storage
+//! holds the mined keys, so storage-trie benchmarks start from it without a deployment
+//! transaction. Any alloc can also be written as a `geth dump`-style state dump, with the
+//! storage roots, code hashes and hashed keys clients derive from it.
+//!
+//! ## Key Functions
+//! - `create2_alloc` / `create_alloc` / `branch_alloc`: Alloc entries of each account result
+//! - `Predeploy::from_branch`: Code and storage of the generated contract for a storage branch
+//! - `Predeploy::with_value`: Given runtime code, with one chosen value in every mined slot
+//! - `merge_into`: Adds the entries to the `alloc` of a base genesis, refusing conflicts
+//! - `write_genesis`: Writes the merged genesis, or a bare `{"alloc": ...}` without a base
+//! - `contract_alloc`: Alloc of a single contract, such as one holding a storage branch
+//! - `state_dump`: Converts an alloc into a state dump with its state root
+
+use crate::account_miner::{
+    AccountBranchResult, Create2MiningResult, CreateMiningResult, keccak256,
+};
+use crate::bytecode::{self, RuntimeKind};
+use crate::layout::{self, SlotLayout};
+use crate::mpt::{self, EMPTY_CODE_HASH, Trie};
+use crate::parse_hex_array;
+use crate::rlp;
+use crate::storage_miner::StorageSlot;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// One account of a genesis `alloc`, with quantities as 0x-prefixed hex
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AllocAccount {
+    pub balance: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<String, String>,
+}
+
+/// Genesis `alloc`: accounts by 0x-prefixed lowercase address
+pub type Alloc = BTreeMap<String, AllocAccount>;
+
+/// Runtime code and storage a contract is placed in genesis with
+pub struct Predeploy {
+    pub code: Vec<u8>,
+    pub storage: Vec<([u8; 32], [u8; 32])>,
+}
+
+impl Predeploy {
+    /// The contract `bytecode::initcode(branch, kind, layout)` deploys when run by `deployer`
+    pub fn from_branch(
+        branch: &[StorageSlot],
+        kind: RuntimeKind,
+        layout: &SlotLayout,
+        deployer: &[u8; 20],
+    ) -> Result<Self, String> {
+        Ok(Predeploy {
+            code: bytecode::runtime_code(branch, kind, layout)?,
+            storage: bytecode::initial_storage(branch, kind, deployer),
+        })
+    }
+
+    /// A contract running `code` with `value` in every mined slot
+    pub fn with_value(branch: &[StorageSlot], code: Vec<u8>, value: &[u8; 32]) -> Self {
+        Predeploy {
+            code,
+            storage: branch
+                .iter()
+                .map(|slot| (slot.storage_key, *value))
+                .collect(),
+        }
+    }
+
+    /// Alloc entry of the deployed contract, with the nonce of 1 EIP-161 gives new contracts
+    fn account(&self) -> AllocAccount {
+        AllocAccount {
+            balance: quantity(&[0u8; 32]),
+            nonce: Some("0x1".to_string()),
+            code: (!self.code.is_empty()).then(|| format!("0x{}", hex::encode(&self.code))),
+            storage: self
+                .storage
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        format!("0x{}", hex::encode(key)),
+                        format!("0x{}", hex::encode(value)),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Alloc of a CREATE2 result: funded auxiliaries, plus the contracts when `predeploy` is given
+pub fn create2_alloc(
+    result: &Create2MiningResult,
+    balance: &[u8; 32],
+    predeploy: Option<&Predeploy>,
+) -> Result<Alloc, String> {
+    let mut alloc = Alloc::new();
+    for contract in &result.contracts {
+        if let Some(predeploy) = predeploy {
+            insert(&mut alloc, &contract.contract_address, predeploy.account())?;
+        }
+        for auxiliary in &contract.auxiliary_accounts {
+            insert(&mut alloc, auxiliary, funded(balance, None))?;
+        }
+    }
+    Ok(alloc)
+}
+
+/// Alloc of a CREATE result: funded auxiliaries and deployers, each deployer starting at the
+/// nonce of its first contract so its next deployments create the mined addresses
+pub fn create_alloc(result: &CreateMiningResult, balance: &[u8; 32]) -> Result<Alloc, String> {
+    let mut deployers: BTreeMap<&str, u64> = BTreeMap::new();
+    for contract in &result.contracts {
+        let nonce = deployers
+            .entry(&contract.deployer)
+            .or_insert(contract.nonce);
+        *nonce = (*nonce).min(contract.nonce);
+    }
+
+    let mut alloc = Alloc::new();
+    for (deployer, nonce) in deployers {
+        let nonce = (nonce > 0).then(|| format!("0x{nonce:x}"));
+        insert(&mut alloc, deployer, funded(balance, nonce))?;
+    }
+    for contract in &result.contracts {
+        for auxiliary in &contract.auxiliary_accounts {
+            insert(&mut alloc, auxiliary, funded(balance, None))?;
+        }
+    }
+    Ok(alloc)
+}
+
+/// Alloc of an account branch result: every mined account funded
+pub fn branch_alloc(result: &AccountBranchResult, balance: &[u8; 32]) -> Result<Alloc, String> {
+    let mut alloc = Alloc::new();
+    for account in &result.accounts {
+        insert(&mut alloc, &account.address, funded(balance, None))?;
+    }
+    Ok(alloc)
+}
+
+/// Alloc holding just the contract at `address`
+pub fn contract_alloc(address: &[u8; 20], predeploy: &Predeploy) -> Alloc {
+    Alloc::from([(format!("0x{}", hex::encode(address)), predeploy.account())])
+}
+
+/// Add the entries to the `alloc` of `base` (created if missing); an address that is
+/// already allocated is an error rather than silently overwritten
+pub fn merge_into(mut base: Value, alloc: &Alloc) -> Result<Value, String> {
+    let genesis = base
+        .as_object_mut()
+        .ok_or("Base genesis is not a JSON object")?;
+    let existing = genesis
+        .entry("alloc")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("Base genesis alloc is not a JSON object")?;
+
+    for (address, account) in alloc {
+        let bare = address.trim_start_matches("0x");
+        if let Some(conflict) = existing
+            .keys()
+            .find(|key| key.trim_start_matches("0x").eq_ignore_ascii_case(bare))
+        {
+            return Err(format!(
+                "{conflict} is already allocated in the base genesis"
+            ));
+        }
+        let account = serde_json::to_value(account).map_err(|e| e.to_string())?;
+        existing.insert(address.clone(), account);
+    }
+    Ok(base)
+}
+
+/// Write `alloc` to `path`, merged into the genesis at `base` when one is given
+pub fn write_genesis(path: &str, base: Option<&str>, alloc: &Alloc) -> Result<(), String> {
+    let base = match base {
+        Some(base) => {
+            let json =
+                fs::read_to_string(base).map_err(|e| format!("Failed to read {base}: {e}"))?;
+            serde_json::from_str(&json).map_err(|e| format!("Invalid genesis JSON {base}: {e}"))?
+        }
+        None => Value::Object(Map::new()),
+    };
+
+    let genesis = merge_into(base, alloc)?;
+    let json =
+        serde_json::to_string_pretty(&genesis).map_err(|e| format!("Failed to serialize: {e}"))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+/// State dump in the format of `geth dump`
+#[derive(Serialize, Debug)]
+pub struct StateDump {
+    /// Root of a state trie holding only the dumped accounts (bare hex, as geth prints it)
+    pub root: String,
+    pub accounts: BTreeMap<String, DumpAccount>,
+}
+
+/// One account of a state dump
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpAccount {
+    /// Balance in wei as a decimal string
+    pub balance: String,
+    pub nonce: u64,
+    /// Storage root
+    pub root: String,
+    pub code_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Values as bare minimal hex, as geth prints them
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<String, String>,
+    pub address: String,
+    /// keccak256 of the address: the account's key in the state trie
+    pub key: String,
+}
+
+/// Convert an alloc into a state dump, deriving the storage roots (keyed by keccak256 of each
+/// slot, as clients store them), code hashes and the root of a state trie holding the accounts
+pub fn state_dump(alloc: &Alloc) -> Result<StateDump, String> {
+    let mut state = Trie::new();
+    let mut accounts = BTreeMap::new();
+
+    for (address, account) in alloc {
+        let balance = parse_quantity(&account.balance)?;
+        let nonce = account.nonce.as_deref().map_or(Ok(0), parse_quantity)?;
+        let nonce = u64::try_from(nonce).map_err(|_| format!("Nonce of {address} exceeds u64"))?;
+        let code = match &account.code {
+            Some(code) => hex::decode(code.trim_start_matches("0x"))
+                .map_err(|e| format!("Invalid code of {address}: {e}"))?,
+            None => Vec::new(),
+        };
+        let code_hash = if code.is_empty() {
+            EMPTY_CODE_HASH
+        } else {
+            keccak256(&code)
+        };
+
+        let mut storage_trie = Trie::new();
+        let mut storage = BTreeMap::new();
+        for (key, value) in &account.storage {
+            let key = parse_hex_array::<32>(key)?;
+            // Genesis files may give values as short hex, which is left-padded
+            let value = layout::parse_word(value)?;
+            let value = rlp::trim_leading_zeros(&value);
+            // Zero values are not stored
+            if !value.is_empty() {
+                storage_trie.insert(&keccak256(&key), rlp::encode_bytes(value));
+                storage.insert(format!("0x{}", hex::encode(key)), hex::encode(value));
+            }
+        }
+        let storage_root = storage_trie.root_hash();
+
+        let address_bytes = parse_hex_array::<20>(address)?;
+        let key = keccak256(&address_bytes);
+        state.insert(
+            &key,
+            mpt::account_rlp(nonce, balance, &storage_root, &code_hash),
+        );
+        accounts.insert(
+            address.clone(),
+            DumpAccount {
+                balance: balance.to_string(),
+                nonce,
+                root: format!("0x{}", hex::encode(storage_root)),
+                code_hash: format!("0x{}", hex::encode(code_hash)),
+                code: (!code.is_empty()).then(|| format!("0x{}", hex::encode(&code))),
+                storage,
+                address: address.clone(),
+                key: format!("0x{}", hex::encode(key)),
+            },
+        );
+    }
+
+    Ok(StateDump {
+        root: hex::encode(state.root_hash()),
+        accounts,
+    })
+}
+
+/// Write the state dump of `alloc` to `path`
+pub fn write_state_dump(path: &str, alloc: &Alloc) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&state_dump(alloc)?)
+        .map_err(|e| format!("Failed to serialize: {e}"))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+/// Account holding only a balance
+fn funded(balance: &[u8; 32], nonce: Option<String>) -> AllocAccount {
+    AllocAccount {
+        balance: quantity(balance),
+        nonce,
+        code: None,
+        storage: BTreeMap::new(),
+    }
+}
+
+/// Insert an account under its normalized address, refusing duplicates
+fn insert(alloc: &mut Alloc, address: &str, account: AllocAccount) -> Result<(), String> {
+    let address = format!("0x{}", hex::encode(parse_hex_array::<20>(address)?));
+    if alloc.contains_key(&address) {
+        return Err(format!("{address} appears twice in the result"));
+    }
+    alloc.insert(address, account);
+    Ok(())
+}
+
+/// A 256-bit word as a minimal 0x-prefixed hex quantity
+fn quantity(word: &[u8; 32]) -> String {
+    let digits = hex::encode(word);
+    match digits.trim_start_matches('0') {
+        "" => "0x0".to_string(),
+        digits => format!("0x{digits}"),
+    }
+}
+
+/// Parse a 0x-prefixed hex quantity (balances beyond u128 are not supported)
+fn parse_quantity(quantity: &str) -> Result<u128, String> {
+    let digits = quantity.trim_start_matches("0x");
+    u128::from_str_radix(if digits.is_empty() { "0" } else { digits }, 16)
+        .map_err(|e| format!("Invalid quantity {quantity}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_miner::ContractWithAuxiliaries;
+    use serde_json::json;
+
+    #[test]
+    fn test_create2_alloc_merges_into_base() {
+        let contract = ContractWithAuxiliaries {
+            salt: format!("0x{}", "00".repeat(32)),
+            contract_address: format!("0x{}", "aa".repeat(20)),
+            auxiliary_accounts: vec![format!("0x{}", "BB".repeat(20))],
+            auxiliary_keys: Vec::new(),
+            auxiliary_shared_nibbles: Vec::new(),
+            trie: None,
+        };
+        let result = Create2MiningResult {
+            deployer: format!("0x{}", "11".repeat(20)),
+            init_code_hash: format!("0x{}", "00".repeat(32)),
+            target_depth: 1,
+            num_contracts: 1,
+            salt_prefix: "0x".to_string(),
+            salt_start: 0,
+            caller: None,
+            chain_id: None,
+            contract_nibbles: 0,
+            contract_target: None,
+            exact: false,
+            total_time: 0.0,
+            contracts: vec![contract],
+        };
+        let mut balance = [0u8; 32];
+        balance[31] = 0x10;
+
+        // Without a predeploy only the auxiliary is allocated
+        let alloc = create2_alloc(&result, &balance, None).unwrap();
+        assert_eq!(alloc.len(), 1);
+        assert_eq!(alloc[&format!("0x{}", "bb".repeat(20))].balance, "0x10");
+
+        let slot = StorageSlot {
+            address: [0x22; 20],
+            storage_key: [0x33; 32],
+            depth: 1,
+            time_taken: 0.0,
+        };
+        let predeploy = Predeploy::from_branch(
+            &[slot],
+            RuntimeKind::Attack,
+            &SlotLayout::default(),
+            &[0x11; 20],
+        )
+        .unwrap();
+        let alloc = create2_alloc(&result, &balance, Some(&predeploy)).unwrap();
+        let deployed = &alloc[&format!("0x{}", "aa".repeat(20))];
+        assert_eq!(deployed.nonce.as_deref(), Some("0x1"));
+        assert!(deployed.code.is_some());
+        assert_eq!(
+            deployed.storage[&format!("0x{}", "33".repeat(32))],
+            format!("0x{}01", "00".repeat(31))
+        );
+
+        let base = json!({ "config": { "chainId": 1337 }, "alloc": {} });
+        let merged = merge_into(base, &alloc).unwrap();
+        assert_eq!(merged["config"]["chainId"], 1337);
+        assert_eq!(merged["alloc"].as_object().unwrap().len(), 2);
+
+        // Allocating the same address again is refused, whatever its spelling
+        let clash = json!({ "alloc": { "BBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB": { "balance": "0x1" } } });
+        assert!(merge_into(clash, &alloc).is_err());
+    }
+
+    #[test]
+    fn test_state_dump_of_storage_contract() {
+        let slot = |key: u8| StorageSlot {
+            address: [key; 20],
+            storage_key: [key; 32],
+            depth: 1,
+            time_taken: 0.0,
+        };
+        let mut value = [0u8; 32];
+        value[30] = 0x01;
+        value[31] = 0x02;
+        let predeploy = Predeploy::with_value(&[slot(0x33), slot(0x44)], vec![0x00], &value);
+        let mut alloc = contract_alloc(&[0xaa; 20], &predeploy);
+        let mut funded_only = funded(&value, None);
+        funded_only
+            .storage
+            .insert(format!("0x{}", "55".repeat(32)), quantity(&[0u8; 32]));
+        alloc.insert(format!("0x{}", "bb".repeat(20)), funded_only);
+
+        let dump = state_dump(&alloc).unwrap();
+        let contract = &dump.accounts[&format!("0x{}", "aa".repeat(20))];
+        assert_eq!(contract.nonce, 1);
+        assert_eq!(contract.storage.len(), 2);
+        assert_eq!(contract.storage[&format!("0x{}", "33".repeat(32))], "0102");
+        assert_eq!(
+            contract.key,
+            format!("0x{}", hex::encode(keccak256(&[0xaa; 20])))
+        );
+        assert_ne!(contract.root, format!("0x{}", hex::encode(mpt::EMPTY_ROOT)));
+
+        // Zero slots are dropped, leaving an empty storage root
+        let account = &dump.accounts[&format!("0x{}", "bb".repeat(20))];
+        assert_eq!(account.balance, "258");
+        assert!(account.storage.is_empty());
+        assert_eq!(account.root, format!("0x{}", hex::encode(mpt::EMPTY_ROOT)));
+        assert_eq!(
+            account.code_hash,
+            format!("0x{}", hex::encode(EMPTY_CODE_HASH))
+        );
+    }
+}
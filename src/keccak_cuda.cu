@@ -5,6 +5,19 @@
 #include <stdint.h>
 #include <stdio.h>
 
+#define MAX_INNER_KEYS 4
+
+// Storage layout of the mined keys, mirrored by `CudaSlotLayout` in cuda_miner.rs
+struct SlotLayout {
+    uint8_t mapping_slot[32];               // Slot of the mapping indexed by the mined key
+    uint8_t inner_keys[MAX_INNER_KEYS][32]; // Fixed keys of mappings nested below it
+    uint64_t array_index;                   // Dynamic array element, if has_array
+    int32_t num_inner_keys;
+    int32_t vyper;                          // 1: keccak(slot . key), 0: keccak(key . slot)
+    int32_t key_left_aligned;               // 1: bytes32 key, value in the high bytes
+    int32_t has_array;
+};
+
 // xorshift64* PRNG - better statistical properties than LCG
 __device__ inline uint64_t xorshift64star(uint64_t* state) {
     uint64_t x = *state;
@@ -109,10 +122,32 @@ __device__ void keccak_f1600(uint64_t state[25]) {
     }
 }
 
-// Calculate storage slot for an address
-__device__ void calculate_storage_slot(uint8_t address[20], uint64_t base_slot, uint8_t output[32]) {
+// Keccak256 of an input that fits in a single block (len < 136 bytes)
+__device__ void keccak256(const uint8_t* input, int len, uint8_t output[32]) {
     uint64_t state[25] = {0};
 
+    // Load input into state (little-endian)
+    for (int i = 0; i < len; i++) {
+        state[i / 8] ^= ((uint64_t)input[i]) << ((i % 8) * 8);
+    }
+
+    // Keccak padding: 0x01 after the input, 0x80 in the last byte of the rate
+    state[len / 8] ^= 0x01ULL << ((len % 8) * 8);
+    state[16] ^= 0x8000000000000000ULL;
+
+    // Apply Keccak-f[1600]
+    keccak_f1600(state);
+
+    // Extract output (first 32 bytes)
+    for (int i = 0; i < 4; i++) {
+        for (int j = 0; j < 8; j++) {
+            output[i * 8 + j] = (state[i] >> (j * 8)) & 0xFF;
+        }
+    }
+}
+
+// Calculate storage slot for an address
+__device__ void calculate_storage_slot(uint8_t address[20], uint64_t base_slot, uint8_t output[32]) {
     // Prepare input: padded address (32 bytes) + slot (32 bytes)
     uint8_t input[64];
 
@@ -126,26 +161,52 @@ __device__ void calculate_storage_slot(uint8_t address[20], uint64_t base_slot,
         input[32 + 24 + i] = (base_slot >> (56 - i * 8)) & 0xFF;
     }
 
-    // Load input into state (little-endian)
-    for (int i = 0; i < 8; i++) {
-        state[i] = 0;
-        for (int j = 0; j < 8; j++) {
-            state[i] |= ((uint64_t)input[i * 8 + j]) << (j * 8);
-        }
-    }
+    keccak256(input, 64, output);
+}
 
-    // Add padding
-    state[8] = 0x01;
-    state[16] = 0x8000000000000000ULL;
+// Add a 64-bit value to a big-endian 256-bit word (wrapping like the EVM)
+__device__ void add_u64_be(uint8_t word[32], uint64_t value) {
+    unsigned int carry = 0;
+    for (int i = 31; i >= 0 && (value != 0 || carry != 0); i--) {
+        unsigned int sum = word[i] + (unsigned int)(value & 0xFF) + carry;
+        word[i] = sum & 0xFF;
+        carry = sum >> 8;
+        value >>= 8;
+    }
+}
 
-    // Apply Keccak-f[1600]
-    keccak_f1600(state);
+// Derive the storage key of a mined key, following SlotLayout::storage_key in layout.rs
+__device__ void derive_storage_key(const SlotLayout& layout, const uint8_t address[20], uint8_t output[32]) {
+    uint8_t input[64];
+    uint8_t* key_word = layout.vyper ? input + 32 : input;
+    uint8_t* slot_word = layout.vyper ? input : input + 32;
+
+    // Mined key, padded to a word
+    int offset = layout.key_left_aligned ? 0 : 12;
+    for (int i = 0; i < 32; i++) key_word[i] = 0;
+    for (int i = 0; i < 20; i++) key_word[offset + i] = address[i];
+    for (int i = 0; i < 32; i++) slot_word[i] = layout.mapping_slot[i];
+    keccak256(input, 64, output);
+
+    // Nested mappings below the mined key
+    for (int k = 0; k < layout.num_inner_keys; k++) {
+        for (int i = 0; i < 32; i++) {
+            key_word[i] = layout.inner_keys[k][i];
+            slot_word[i] = output[i];
+        }
+        keccak256(input, 64, output);
+    }
 
-    // Extract output (first 32 bytes)
-    for (int i = 0; i < 4; i++) {
-        for (int j = 0; j < 8; j++) {
-            output[i * 8 + j] = (state[i] >> (j * 8)) & 0xFF;
+    // Dynamic array element: keccak(slot) + i for Solidity, slot + 1 + i for Vyper
+    if (layout.has_array) {
+        if (layout.vyper) {
+            add_u64_be(output, 1);
+        } else {
+            uint8_t array_slot[32];
+            for (int i = 0; i < 32; i++) array_slot[i] = output[i];
+            keccak256(array_slot, 32, output);
         }
+        add_u64_be(output, layout.array_index);
     }
 }
 
@@ -171,11 +232,20 @@ __device__ bool check_nibble_prefix(const uint8_t* a, const uint8_t* b, int nibb
     return true;
 }
 
+// In exact mode the nibble after the shared prefix must differ from the target's
+__device__ bool check_next_nibble_differs(const uint8_t* a, const uint8_t* b, int nibbles) {
+    if (nibbles >= 64) return true;
+
+    uint8_t mask = (nibbles % 2 == 0) ? 0xF0 : 0x0F;
+    return (a[nibbles / 2] & mask) != (b[nibbles / 2] & mask);
+}
+
 // CUDA kernel for mining addresses with specific storage key prefixes
 __global__ void mine_storage_slots(
-    uint8_t* target_prefix,      // Target storage key prefix to match
+    uint8_t* target_prefix,      // Target trie key (keccak256 of a storage key) prefix to match
     int required_nibbles,         // Number of nibbles that must match
-    uint64_t base_slot,          // ERC20 balance mapping slot (usually 0)
+    int exact,                    // 1 if the following nibble must differ
+    SlotLayout layout,           // Where the mined key's value is stored
     uint64_t start_nonce,        // Starting nonce for this kernel
     uint64_t max_attempts,       // Maximum attempts per thread
     uint8_t* result_address,     // Output: found address (20 bytes)
@@ -189,6 +259,7 @@ __global__ void mine_storage_slots(
     for (uint64_t attempt = 0; attempt < max_attempts && *found == 0; attempt++) {
         uint8_t address[20];
         uint8_t storage_key[32];
+        uint8_t trie_key[32];
 
         // Generate pseudo-random address using xorshift64*
         // Initialize state with nonce+attempt, ensuring it's never 0
@@ -203,10 +274,14 @@ __global__ void mine_storage_slots(
         }
 
         // Calculate storage slot
-        calculate_storage_slot(address, base_slot, storage_key);
+        derive_storage_key(layout, address, storage_key);
+
+        // The storage trie is keyed by keccak256(slot), so the prefix applies to that
+        keccak256(storage_key, 32, trie_key);
 
         // Check if it matches the required prefix
-        if (check_nibble_prefix(storage_key, target_prefix, required_nibbles)) {
+        if (check_nibble_prefix(trie_key, target_prefix, required_nibbles) &&
+            (!exact || check_next_nibble_differs(trie_key, target_prefix, required_nibbles))) {
             // Use atomic compare-and-swap to ensure only one thread wins
             int old = atomicCAS(found, 0, 1);
             if (old == 0) {
@@ -245,6 +320,26 @@ extern "C" {
         cudaFree(d_result);
     }
 
+    // Verification function to test CUDA storage key derivation against SlotLayout::storage_key
+    void cuda_verify_storage_key(
+        const SlotLayout* layout,
+        uint8_t* test_address,  // 20 bytes
+        uint8_t* result_storage_key  // 32 bytes output
+    ) {
+        uint8_t *d_addr, *d_result;
+        cudaMalloc(&d_addr, 20);
+        cudaMalloc(&d_result, 32);
+        cudaMemcpy(d_addr, test_address, 20, cudaMemcpyHostToDevice);
+
+        extern __global__ void verify_storage_key_kernel(SlotLayout layout, uint8_t* addr, uint8_t* result);
+        verify_storage_key_kernel<<<1, 1>>>(*layout, d_addr, d_result);
+        cudaDeviceSynchronize();
+
+        cudaMemcpy(result_storage_key, d_result, 32, cudaMemcpyDeviceToHost);
+        cudaFree(d_addr);
+        cudaFree(d_result);
+    }
+
     // Debug function to get a generated address and its storage key from the PRNG
     void cuda_debug_prng(
         uint64_t seed,
@@ -275,6 +370,14 @@ __global__ void verify_keccak_kernel(uint8_t* addr, uint64_t slot, uint8_t* resu
     for (int i = 0; i < 32; i++) result[i] = storage_key[i];
 }
 
+__global__ void verify_storage_key_kernel(SlotLayout layout, uint8_t* addr, uint8_t* result) {
+    uint8_t address[20];
+    uint8_t storage_key[32];
+    for (int i = 0; i < 20; i++) address[i] = addr[i];
+    derive_storage_key(layout, address, storage_key);
+    for (int i = 0; i < 32; i++) result[i] = storage_key[i];
+}
+
 __global__ void debug_prng_kernel(uint64_t seed, uint64_t slot, uint8_t* result_addr, uint8_t* result_key) {
     uint8_t address[20];
     uint8_t storage_key[32];
@@ -320,7 +423,8 @@ extern "C" {
     void cuda_mine_storage_slot(
         uint8_t* target_prefix,
         int required_nibbles,
-        uint64_t base_slot,
+        int exact,
+        const SlotLayout* layout,
         uint8_t* result_address,
         uint8_t* result_storage_key,
         bool* found,
@@ -346,7 +450,8 @@ extern "C" {
         mine_storage_slots<<<blocks, threads_per_block>>>(
             d_target,
             required_nibbles,
-            base_slot,
+            exact,
+            *layout,
             start_nonce,
             attempts_per_thread,
             d_result_addr,
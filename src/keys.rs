@@ -0,0 +1,63 @@
+//! # Keys Module
+//!
+//! secp256k1 helpers for accounts that should be controllable: mined EOAs whose private keys
+//! are written to the results, so they can sign transactions later.
+//!
+//! ## Key Functions
+//! - `address_of`: Ethereum address of a secret key
+//! - `format_secret` / `parse_secret`: Hex encoding of secret keys in the JSON results
+
+use crate::account_miner::keccak256;
+use secp256k1::{PublicKey, Secp256k1, SecretKey, Signing};
+
+/// Ethereum address of a secret key: the last 20 bytes of the keccak256 hash of the
+/// uncompressed public key without its 0x04 tag
+pub fn address_of<C: Signing>(secp: &Secp256k1<C>, secret: &SecretKey) -> [u8; 20] {
+    let public = PublicKey::from_secret_key(secp, secret).serialize_uncompressed();
+    let hash = keccak256(&public[1..]);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Secret key as 0x-prefixed hex
+pub fn format_secret(secret: &SecretKey) -> String {
+    format!("0x{}", hex::encode(secret.secret_bytes()))
+}
+
+/// Parse a 0x-prefixed (or bare) hex secret key
+pub fn parse_secret(hex_str: &str) -> Result<SecretKey, String> {
+    let bytes: [u8; 32] = crate::parse_hex_array(hex_str)?;
+    SecretKey::from_slice(&bytes).map_err(|e| format!("Invalid secret key: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_of_known_keys() {
+        let secp = Secp256k1::signing_only();
+
+        let one = parse_secret(&format!("0x{}01", "00".repeat(31))).unwrap();
+        assert_eq!(
+            hex::encode(address_of(&secp, &one)),
+            "7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+
+        let key =
+            parse_secret("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                .unwrap();
+        assert_eq!(
+            hex::encode(address_of(&secp, &key)),
+            "2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+        );
+        assert_eq!(parse_secret(&format_secret(&key)).unwrap(), key);
+    }
+
+    #[test]
+    fn test_parse_secret_rejects_zero() {
+        assert!(parse_secret(&"00".repeat(32)).is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! # Slot Layout Module
+//!
+//! Describes where a contract stores the value belonging to a mined mapping key, so the miners
+//! can target layouts other than an ERC20 `balanceOf` at slot 0: USDC-style proxies with the
+//! balances at a later slot, ERC-7201 namespaced storage of upgradeable tokens, `allowance`
+//! nested mappings, dynamic arrays stored under a key, and Vyper's storage layout.
+//!
+//! The mined key is always a random 160-bit value. Address and uint256 keys are left-padded to
+//! a word, bytes32 keys right-padded.
+//!
+//! ## Key Functions
+//! - `SlotLayout::storage_key`: Derives the storage key for a mined key
+//! - `erc7201_root`: Computes the storage root of an ERC-7201 namespace
+//! - `parse_word`: Parses a slot number or fixed key given on the command line
+
+use crate::account_miner::keccak256;
+use serde::{Deserialize, Serialize};
+
+/// Compiler whose storage layout rules apply
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutStyle {
+    /// Mapping values at `keccak(key . slot)`, array elements at `keccak(slot) + i`
+    #[default]
+    Solidity,
+    /// Mapping values at `keccak(slot . key)`, array elements at `slot + 1 + i`
+    Vyper,
+}
+
+/// Solidity type of the mined mapping key
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    #[default]
+    Address,
+    Uint256,
+    Bytes32,
+}
+
+/// Storage location of the values written for mined keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotLayout {
+    pub style: LayoutStyle,
+    pub key_type: KeyType,
+    /// Slot of the outermost mapping
+    pub base_slot: [u8; 32],
+    /// Fixed keys of the mappings enclosing the mined key, outermost first
+    pub outer_keys: Vec<[u8; 32]>,
+    /// Fixed keys of the mappings nested below the mined key, outermost first
+    pub inner_keys: Vec<[u8; 32]>,
+    /// Element written when the value under the mined key is a dynamic array
+    pub array_index: Option<u64>,
+    /// Slot of the mapping indexed by the mined key, with the outer keys already applied
+    mapping_slot: [u8; 32],
+}
+
+/// Serializable description of a `SlotLayout`, written to the result JSON
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutInfo {
+    #[serde(default)]
+    pub style: LayoutStyle,
+    #[serde(default)]
+    pub key_type: KeyType,
+    pub base_slot: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outer_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inner_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array_index: Option<u64>,
+}
+
+impl Default for SlotLayout {
+    /// `mapping(address => uint256)` at slot 0, the ERC20 balances of the generated contracts
+    fn default() -> Self {
+        SlotLayout::new(
+            LayoutStyle::Solidity,
+            KeyType::Address,
+            [0; 32],
+            Vec::new(),
+            Vec::new(),
+            None,
+        )
+    }
+}
+
+impl SlotLayout {
+    pub fn new(
+        style: LayoutStyle,
+        key_type: KeyType,
+        base_slot: [u8; 32],
+        outer_keys: Vec<[u8; 32]>,
+        inner_keys: Vec<[u8; 32]>,
+        array_index: Option<u64>,
+    ) -> Self {
+        let mapping_slot = outer_keys
+            .iter()
+            .fold(base_slot, |slot, key| mapping_hash(style, key, &slot));
+
+        SlotLayout {
+            style,
+            key_type,
+            base_slot,
+            outer_keys,
+            inner_keys,
+            array_index,
+            mapping_slot,
+        }
+    }
+
+    /// Whether the layout is a plain Solidity `mapping(address => ...)` at `slot`, the only
+    /// layout a `balanceOf[addr]` assignment in the generated contract can express
+    pub fn is_plain_address_mapping(&self, slot: u64) -> bool {
+        *self
+            == SlotLayout {
+                base_slot: word_from_u64(slot),
+                ..SlotLayout::default()
+            }
+    }
+
+    /// Storage key written for the mined `key`
+    pub fn storage_key(&self, key: &[u8; 20]) -> [u8; 32] {
+        let mut slot = mapping_hash(self.style, &self.encode_key(key), &self.mapping_slot);
+        for inner in &self.inner_keys {
+            slot = mapping_hash(self.style, inner, &slot);
+        }
+
+        match (self.array_index, self.style) {
+            (None, _) => slot,
+            (Some(index), LayoutStyle::Solidity) => add_u64(&keccak256(&slot), index),
+            (Some(index), LayoutStyle::Vyper) => add_u64(&add_u64(&slot, 1), index),
+        }
+    }
+
+    /// Encode the mined key as the word hashed with the mapping slot
+    pub fn encode_key(&self, key: &[u8; 20]) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        match self.key_type {
+            KeyType::Address | KeyType::Uint256 => word[12..].copy_from_slice(key),
+            KeyType::Bytes32 => word[..20].copy_from_slice(key),
+        }
+        word
+    }
+
+    /// Slot of the mapping indexed by the mined key
+    #[cfg(feature = "cuda")]
+    pub fn mapping_slot(&self) -> &[u8; 32] {
+        &self.mapping_slot
+    }
+
+    pub fn info(&self) -> LayoutInfo {
+        LayoutInfo {
+            style: self.style,
+            key_type: self.key_type,
+            base_slot: format!("0x{}", hex::encode(self.base_slot)),
+            outer_keys: self
+                .outer_keys
+                .iter()
+                .map(|key| format!("0x{}", hex::encode(key)))
+                .collect(),
+            inner_keys: self
+                .inner_keys
+                .iter()
+                .map(|key| format!("0x{}", hex::encode(key)))
+                .collect(),
+            array_index: self.array_index,
+        }
+    }
+
+    /// One-line description for logs, e.g. `solidity mapping(address) at slot 0x09`
+    pub fn describe(&self) -> String {
+        let slot = hex::encode(crate::rlp::trim_leading_zeros(&self.base_slot));
+        let mut description = format!(
+            "{:?} mapping({:?}) at slot 0x{}",
+            self.style,
+            self.key_type,
+            if slot.is_empty() { "0" } else { &slot }
+        )
+        .to_lowercase();
+        if !self.outer_keys.is_empty() || !self.inner_keys.is_empty() {
+            description += &format!(
+                ", {} outer / {} inner fixed keys",
+                self.outer_keys.len(),
+                self.inner_keys.len()
+            );
+        }
+        if let Some(index) = self.array_index {
+            description += &format!(", array element {index}");
+        }
+        description
+    }
+}
+
+impl Default for LayoutInfo {
+    /// Results written before layouts were configurable used the default layout
+    fn default() -> Self {
+        SlotLayout::default().info()
+    }
+}
+
+impl LayoutInfo {
+    /// Rebuild the layout from a deserialized result
+    pub fn to_layout(&self) -> Result<SlotLayout, String> {
+        let parse_all = |keys: &[String]| -> Result<Vec<[u8; 32]>, String> {
+            keys.iter().map(|key| parse_word(key)).collect()
+        };
+
+        Ok(SlotLayout::new(
+            self.style,
+            self.key_type,
+            parse_word(&self.base_slot)?,
+            parse_all(&self.outer_keys)?,
+            parse_all(&self.inner_keys)?,
+            self.array_index,
+        ))
+    }
+}
+
+/// Hash a mapping key with the mapping's slot in the order the compiler uses
+fn mapping_hash(style: LayoutStyle, key: &[u8; 32], slot: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    let (first, second) = match style {
+        LayoutStyle::Solidity => (key, slot),
+        LayoutStyle::Vyper => (slot, key),
+    };
+    data[..32].copy_from_slice(first);
+    data[32..].copy_from_slice(second);
+    keccak256(&data)
+}
+
+/// Storage root of an ERC-7201 namespace:
+/// `keccak256(abi.encode(uint256(keccak256(id)) - 1)) & ~bytes32(uint256(0xff))`
+pub fn erc7201_root(namespace: &str) -> [u8; 32] {
+    let mut id = keccak256(namespace.as_bytes());
+
+    // Subtract one, borrowing through trailing zero bytes
+    for byte in id.iter_mut().rev() {
+        let (value, borrow) = byte.overflowing_sub(1);
+        *byte = value;
+        if !borrow {
+            break;
+        }
+    }
+
+    let mut root = keccak256(&id);
+    root[31] = 0;
+    root
+}
+
+/// Add to a big-endian 256-bit word, wrapping on overflow like the EVM
+pub fn add_u64(word: &[u8; 32], value: u64) -> [u8; 32] {
+    let mut out = *word;
+    let mut carry = value as u128;
+    for byte in out.iter_mut().rev() {
+        if carry == 0 {
+            break;
+        }
+        let sum = *byte as u128 + (carry & 0xff);
+        *byte = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+    out
+}
+
+fn word_from_u64(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Parse a 256-bit word: decimal (up to u128), or 0x-prefixed hex of at most 32 bytes that is
+/// left-padded, so an address can be given as a fixed mapping key
+pub fn parse_word(s: &str) -> Result<[u8; 32], String> {
+    let Some(digits) = s.strip_prefix("0x") else {
+        let value: u128 = s
+            .parse()
+            .map_err(|e| format!("Invalid slot or key {s}: {e}"))?;
+        let mut word = [0u8; 32];
+        word[16..].copy_from_slice(&value.to_be_bytes());
+        return Ok(word);
+    };
+
+    let digits = if digits.len() % 2 == 1 {
+        format!("0{digits}")
+    } else {
+        digits.to_string()
+    };
+    let bytes = hex::decode(&digits).map_err(|e| format!("Invalid hex {s}: {e}"))?;
+    if bytes.len() > 32 {
+        return Err(format!("{s} is longer than 32 bytes"));
+    }
+
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_miner::calculate_storage_slot;
+
+    const OWNER: [u8; 20] = [0x11; 20];
+    const SPENDER: [u8; 20] = [0x22; 20];
+
+    fn address_word(address: &[u8; 20]) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(address);
+        word
+    }
+
+    #[test]
+    fn test_default_layout_matches_balance_mapping() {
+        let layout = SlotLayout::default();
+        assert_eq!(
+            layout.storage_key(&OWNER),
+            calculate_storage_slot(&OWNER, 0)
+        );
+        assert!(layout.is_plain_address_mapping(0));
+    }
+
+    /// USDC keeps `balances` at slot 9 and `allowed` at slot 10
+    #[test]
+    fn test_usdc_style_slots() {
+        let balances = SlotLayout::new(
+            LayoutStyle::Solidity,
+            KeyType::Address,
+            parse_word("9").unwrap(),
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+        assert_eq!(
+            hex::encode(balances.storage_key(&OWNER)),
+            "233b1b49de63438bb1ac1a57ef81babcc52ccd4555c968bb144593ea539bbebc"
+        );
+
+        // allowed[owner][spender], mining the spender
+        let allowance = SlotLayout::new(
+            LayoutStyle::Solidity,
+            KeyType::Address,
+            parse_word("10").unwrap(),
+            vec![address_word(&OWNER)],
+            Vec::new(),
+            None,
+        );
+        let expected = hex::encode(allowance.storage_key(&SPENDER));
+        assert_eq!(
+            expected,
+            "d3cdd44d7d7ece8c5edc9108997693ec601b7c3096f834862b79eeb60139b9f7"
+        );
+
+        // Mining the owner with a fixed spender lands on the same slot
+        let by_owner = SlotLayout::new(
+            LayoutStyle::Solidity,
+            KeyType::Address,
+            parse_word("10").unwrap(),
+            Vec::new(),
+            vec![address_word(&SPENDER)],
+            None,
+        );
+        assert_eq!(hex::encode(by_owner.storage_key(&OWNER)), expected);
+    }
+
+    #[test]
+    fn test_vyper_and_array_layouts() {
+        let vyper = SlotLayout::new(
+            LayoutStyle::Vyper,
+            KeyType::Address,
+            word_from_u64(3),
+            Vec::new(),
+            Vec::new(),
+            Some(2),
+        );
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(&word_from_u64(3));
+        data[32..].copy_from_slice(&address_word(&OWNER));
+        assert_eq!(vyper.storage_key(&OWNER), add_u64(&keccak256(&data), 3));
+
+        let solidity = SlotLayout::new(
+            LayoutStyle::Solidity,
+            KeyType::Bytes32,
+            word_from_u64(1),
+            Vec::new(),
+            Vec::new(),
+            Some(5),
+        );
+        let mut key = [0u8; 32];
+        key[..20].copy_from_slice(&OWNER);
+        data[..32].copy_from_slice(&key);
+        data[32..].copy_from_slice(&word_from_u64(1));
+        assert_eq!(
+            solidity.storage_key(&OWNER),
+            add_u64(&keccak256(&keccak256(&data)), 5)
+        );
+    }
+
+    /// Roots from the ERC-7201 specification and OpenZeppelin's ERC20Upgradeable
+    #[test]
+    fn test_erc7201_root() {
+        assert_eq!(
+            hex::encode(erc7201_root("example.main")),
+            "183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500"
+        );
+        assert_eq!(
+            hex::encode(erc7201_root("openzeppelin.storage.ERC20")),
+            "52c63247e1f47db19d5ce0460030c497f067ca4cebf71ba98eeadabe20bace00"
+        );
+    }
+
+    #[test]
+    fn test_add_u64_carries_and_wraps() {
+        let mut word = [0u8; 32];
+        word[31] = 0xff;
+        word[30] = 0xff;
+        let sum = add_u64(&word, 1);
+        assert_eq!(sum[29..], [1, 0, 0]);
+        assert_eq!(add_u64(&[0xff; 32], 1), [0; 32]);
+    }
+
+    #[test]
+    fn test_layout_info_round_trip() {
+        let layout = SlotLayout::new(
+            LayoutStyle::Vyper,
+            KeyType::Uint256,
+            parse_word("0x52c63247e1f47db19d5ce0460030c497f067ca4cebf71ba98eeadabe20bace00")
+                .unwrap(),
+            vec![address_word(&OWNER)],
+            vec![address_word(&SPENDER)],
+            Some(7),
+        );
+        assert_eq!(layout.info().to_layout().unwrap(), layout);
+    }
+}
@@ -1,21 +1,188 @@
-use clap::Parser;
-use log::info;
-use std::process::Command;
+use account_miner::{CreateDeployers, SaltRange};
+use binary_tree::{StemKind, StemSource};
+use bytecode::RuntimeKind;
+use clap::{Parser, Subcommand};
+use factories::SaltGuard;
+use layout::{KeyType, LayoutStyle, SlotLayout};
+use log::{error, info, warn};
+use snapshot::{SnapshotAnchor, SnapshotKind};
+use std::process::{self, Command};
 use std::time::Instant;
+use storage_miner::ContractMode;
 
 mod account_miner;
+mod binary_tree;
+mod bytecode;
+mod checkpoint;
+mod engine;
+mod factories;
+mod forest;
+mod genesis;
+mod keys;
+mod layout;
+mod mpt;
+mod rlp;
+mod search;
+mod snapshot;
+mod statetest;
 mod storage_miner;
+mod targets;
+mod transactions;
 
 #[cfg(feature = "cuda")]
 mod cuda_miner;
 
+/// Path the generated Solidity contract is written to
+const GENERATED_CONTRACT_PATH: &str = "contracts/WorstCaseERC20.sol";
+
+/// Path the generated initcode is written to
+const GENERATED_INITCODE_PATH: &str = "contracts/WorstCaseERC20.hex";
+
+/// Path the exported genesis is written to
+const GENESIS_PATH: &str = "genesis.json";
+
+/// Path the signed funding transactions are written to
+const FUNDING_PATH: &str = "funding_transactions.txt";
+
+/// Paths the factory calls deploying the mined contracts are written to
+const DEPLOYMENTS_PATH: &str = "deployments.json";
+const DEPLOYMENT_TRANSACTIONS_PATH: &str = "deployment_transactions.txt";
+
+/// Path the state test fixture is written to
+const STATE_TEST_PATH: &str = "state_test.json";
+
+/// How the auto-generated CREATE2 contract is turned into init code
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Compiler {
+    /// Assemble the initcode in-process (deterministic, no solc needed)
+    Native,
+    /// Render the Solidity template and compile it with solc
+    Solc,
+}
+
+/// Output format of the `generate` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum GenerateFormat {
+    /// Solidity source rendered from the template
+    Solidity,
+    /// Hex-encoded initcode assembled in-process
+    Initcode,
+}
+
+/// Output format of the `genesis` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum GenesisFormat {
+    /// Genesis `alloc` section, merged into --base when given
+    Alloc,
+    /// `geth dump`-style state dump with storage roots, code hashes and the state root
+    Dump,
+}
+
 /// A mining program to create deep branches in ERC20 contract storage and account trie
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// Target depth for the storage/account branch
-    #[arg(short, long)]
-    depth: usize,
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Mine storage slots that form a deep branch in an ERC20 storage trie
+    Storage(StorageArgs),
+    /// Mine CREATE2 contracts with auxiliary accounts for a deep account trie branch
+    Create2(Create2Args),
+    /// Mine CREATE (nonce-based) contracts with auxiliary accounts for a deep account trie branch
+    Create(CreateArgs),
+    /// Mine a chain of accounts that forms a deep branch in the account trie
+    Accounts(AccountsArgs),
+    /// Mine storage slots or accounts whose EIP-7864 binary tree stems form a deep branch
+    Stems(StemsArgs),
+    /// Generate the Solidity contract from a storage mining result
+    Generate(GenerateArgs),
+    /// Verify any mining result file
+    Verify(VerifyArgs),
+    /// Export mined accounts or a storage branch as a genesis alloc or a state dump
+    Genesis(GenesisArgs),
+    /// Write signed EIP-1559 transactions that fund every auxiliary account of a CREATE2 result
+    Fund(FundArgs),
+    /// Write the factory calls (or signed transactions) deploying every contract of a CREATE2 result
+    Deploy(DeployArgs),
+    /// Write a GeneralStateTest fixture attacking a storage branch or the accounts of a CREATE2 result
+    StateTest(StateTestArgs),
+}
+
+/// Where the mined keys' values are stored, shared by every command that mines storage slots
+#[derive(clap::Args, Debug)]
+struct LayoutArgs {
+    /// Slot of the mapping holding the mined keys, or an explicit 32-byte storage root
+    /// (decimal or 0x-prefixed hex)
+    #[arg(long, default_value = "0", value_parser = layout::parse_word)]
+    base_slot: [u8; 32],
+
+    /// ERC-7201 namespace id whose storage root replaces --base-slot
+    /// (e.g. openzeppelin.storage.ERC20)
+    #[arg(long, conflicts_with = "base_slot")]
+    namespace: Option<String>,
+
+    /// Offset of the mapping from the storage root, i.e. its position in the namespaced struct
+    /// (OpenZeppelin's ERC20Storage: 0 = _balances, 1 = _allowances)
+    #[arg(long, default_value_t = 0)]
+    slot_offset: u64,
+
+    /// Type of the mined mapping key
+    #[arg(long, value_enum, default_value_t = KeyType::Address)]
+    key_type: KeyType,
+
+    /// Compiler storage layout (mapping hash order and dynamic array placement)
+    #[arg(long, value_enum, default_value_t = LayoutStyle::Solidity)]
+    layout: LayoutStyle,
+
+    /// Fixed key of a mapping enclosing the mined key, outermost first; repeatable
+    /// (e.g. the owner when mining `allowance` spenders)
+    #[arg(long = "outer-key", value_parser = layout::parse_word)]
+    outer_keys: Vec<[u8; 32]>,
+
+    /// Fixed key of a mapping nested below the mined key, outermost first; repeatable
+    /// (e.g. the spender when mining `allowance` owners)
+    #[arg(long = "inner-key", value_parser = layout::parse_word)]
+    inner_keys: Vec<[u8; 32]>,
+
+    /// Write element N of a dynamic array stored under the mined key
+    #[arg(long)]
+    array_index: Option<u64>,
+}
+
+impl LayoutArgs {
+    fn slot_layout(&self) -> SlotLayout {
+        let root = match &self.namespace {
+            Some(namespace) => {
+                let root = layout::erc7201_root(namespace);
+                info!(
+                    "ERC-7201 namespace {namespace}: storage root 0x{}",
+                    hex::encode(root)
+                );
+                root
+            }
+            None => self.base_slot,
+        };
+
+        SlotLayout::new(
+            self.layout,
+            self.key_type,
+            layout::add_u64(&root, self.slot_offset),
+            self.outer_keys.clone(),
+            self.inner_keys.clone(),
+            self.array_index,
+        )
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct StorageArgs {
+    /// Target depth for the storage branch
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..=64))]
+    depth: u64,
 
     /// Number of threads to use for mining (default: number of CPU cores)
     #[arg(short, long, default_value_t = num_cpus::get())]
@@ -25,131 +192,1421 @@ struct Args {
     #[arg(long)]
     cuda: bool,
 
-    /// Deployer address for CREATE2 (hex string, default: 0x0000...)
+    /// Output file for storage mining results JSON
+    #[arg(short, long, default_value = "storage_slots.json")]
+    output: String,
+
+    /// Output path for the generated Solidity contract
+    #[arg(long, default_value = GENERATED_CONTRACT_PATH)]
+    contract_output: String,
+
+    /// How the generated contract writes the mined slots
+    #[arg(long, value_enum, default_value_t = ContractMode::Sstore)]
+    contract_mode: ContractMode,
+
+    /// Existing storage slots of the target contract (one hex slot per line, or a JSON array);
+    /// mining continues below the deepest path among their hashed keys
+    #[arg(long)]
+    snapshot: Option<String>,
+
+    /// Continue from a checkpoint (<output>.checkpoint) or an earlier result of lower depth
     #[arg(long)]
+    resume: Option<String>,
+
+    /// Number of independent branches, each rooted under a different first nibble
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=16),
+          conflicts_with_all = ["snapshot", "resume"])]
+    branches: u64,
+
+    /// Also mine a key for every empty child of the branch nodes along each branch, so the
+    /// whole path down to the deepest keys consists of full branch nodes
+    #[arg(long, conflicts_with_all = ["snapshot", "resume"])]
+    fan_out: bool,
+
+    /// Require each level to share exactly its required nibbles with the previous one, so
+    /// every level adds a branch node instead of skipping past it
+    #[arg(long)]
+    exact: bool,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct CreateArgs {
+    /// Target depth for the account branch of each contract
+    #[arg(short, long, value_parser = nibble_count())]
+    depth: usize,
+
+    /// Number of contracts to deploy via CREATE
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    num_contracts: u64,
+
+    /// Existing deployer account whose consecutive nonces deploy the contracts
+    #[arg(long, required_unless_present = "deployer_nibbles")]
     deployer: Option<String>,
 
+    /// Nonce of the deployer's first contract
+    #[arg(long, default_value_t = 0, requires = "deployer")]
+    nonce_start: u64,
+
+    /// Instead of a given deployer, mine one deployer key per contract whose first contract
+    /// (nonce 0) shares this many nibbles with --contract-target (or the first contract)
+    #[arg(long, conflicts_with = "deployer", value_parser = nibble_count())]
+    deployer_nibbles: Option<usize>,
+
+    /// Hashed account key (32 bytes) or address (20 bytes, hashed) the contracts are mined
+    /// towards
+    #[arg(long, requires = "deployer_nibbles")]
+    contract_target: Option<String>,
+
+    /// Mine the auxiliary accounts as EOAs and write their private keys to the output
+    #[arg(long)]
+    eoa_keys: bool,
+
+    /// Require each auxiliary to share exactly its depth in nibbles with the contract hash
+    #[arg(long)]
+    exact: bool,
+
+    /// Find all contracts first, then mine their auxiliaries together, testing every
+    /// candidate against the outstanding targets of all contracts at once
+    #[arg(long)]
+    batch: bool,
+
+    /// Number of threads to use for mining (default: number of CPU cores)
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    threads: usize,
+
+    /// Output file for CREATE accounts JSON
+    #[arg(short, long, default_value = "create_accounts.json")]
+    output: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct AccountsArgs {
+    /// Number of accounts to mine, each one nibble deeper than the previous
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..=64))]
+    depth: u64,
+
+    /// Number of threads to use for mining (default: number of CPU cores)
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    threads: usize,
+
+    /// Existing hashed account keys or addresses (one per line, or a JSON array);
+    /// mining continues below the deepest path among them
+    #[arg(long)]
+    snapshot: Option<String>,
+
+    /// Require each account to share exactly its required nibbles with the previous one
+    #[arg(long)]
+    exact: bool,
+
+    /// Output file for the account branch JSON
+    #[arg(short, long, default_value = "account_branch.json")]
+    output: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct StemsArgs {
+    /// Number of keys to mine, each one bit deeper in the binary tree than the previous
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..=binary_tree::STEM_BITS as u64))]
+    depth: u64,
+
+    /// Mine storage slots of --contract or accounts
+    #[arg(long, value_enum, default_value_t = StemKind::Storage)]
+    kind: StemKind,
+
+    /// Contract whose storage holds the mined slots; its address is part of every tree key
+    #[arg(long, value_parser = parse_address)]
+    contract: Option<[u8; 20]>,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+
+    /// Number of threads to use for mining (default: number of CPU cores)
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    threads: usize,
+
+    /// Require each stem to share exactly its required bits with the previous one
+    #[arg(long)]
+    exact: bool,
+
+    /// Output file for the stem branch JSON
+    #[arg(short, long, default_value = "stem_branch.json")]
+    output: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct Create2Args {
+    /// Target depth for the account branch of each contract
+    #[arg(short, long, value_parser = nibble_count())]
+    depth: usize,
+
     /// Number of contracts to deploy via CREATE2
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    num_contracts: u64,
+
+    /// Deployer address for CREATE2 (hex string, default: 0x0000...)
+    #[arg(long)]
+    deployer: Option<String>,
+
+    /// Hex bytes every salt starts with, e.g. the --caller address followed by 0x00 or 0x01
+    /// to bind CreateX salts to it; the salt counter fills the remaining low bytes
+    #[arg(long, default_value = "0x")]
+    salt_prefix: String,
+
+    /// Account that will call CreateX, which binds salts starting with it to that caller
+    /// (CreateX deployer only)
+    #[arg(long, value_parser = parse_address)]
+    caller: Option<[u8; 20]>,
+
+    /// Chain id CreateX binds cross-chain protected salts to (CreateX deployer only)
     #[arg(long)]
-    num_contracts: Option<usize>,
+    chain_id: Option<u64>,
+
+    /// Counter value of the first salt; runs with disjoint counter ranges mine disjoint
+    /// contract sets
+    #[arg(long, default_value_t = 0)]
+    salt_start: u64,
 
-    /// Path to contract init code for CREATE2 hash calculation
+    /// Search salts so that every contract's hashed address shares this many nibbles with
+    /// --contract-target (or the first contract); auxiliaries then only cover deeper levels
+    #[arg(long, default_value_t = 0, value_parser = nibble_count())]
+    contract_nibbles: usize,
+
+    /// Hashed account key (32 bytes) or address (20 bytes, hashed) the contracts are mined
+    /// towards
+    #[arg(long, requires = "contract_nibbles")]
+    contract_target: Option<String>,
+
+    /// Mine the auxiliary accounts as EOAs and write their private keys to the output, so
+    /// they can send transactions (much slower per attempt than plain addresses)
+    #[arg(long)]
+    eoa_keys: bool,
+
+    /// Require each auxiliary to share exactly its depth in nibbles with the contract hash
+    #[arg(long)]
+    exact: bool,
+
+    /// Find all contracts first, then mine their auxiliaries together, testing every
+    /// candidate against the outstanding targets of all contracts at once
+    #[arg(long)]
+    batch: bool,
+
+    /// Path to contract init code for CREATE2 hash calculation (.sol, .hex/.bin or raw bytes)
     #[arg(long)]
     init_code: Option<String>,
 
+    /// How the contract is built when no --init-code is given
+    #[arg(long, value_enum, default_value_t = Compiler::Native)]
+    compiler: Compiler,
+
+    /// Runtime deployed by natively assembled init code
+    #[arg(long, value_enum, default_value_t = RuntimeKind::Attack)]
+    runtime: RuntimeKind,
+
+    /// How the solc-compiled contract writes the mined slots
+    #[arg(long, value_enum, default_value_t = ContractMode::Sstore)]
+    contract_mode: ContractMode,
+
+    /// Number of threads to use for mining (default: number of CPU cores)
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    threads: usize,
+
     /// Output file for CREATE2 accounts JSON
-    #[arg(long, default_value = "create2_accounts.json")]
-    accounts_output: String,
+    #[arg(
+        short,
+        long,
+        visible_alias = "accounts-output",
+        default_value = "create2_accounts.json"
+    )]
+    output: String,
+
+    /// Continue from a checkpoint (<output>.checkpoint) mined with the same deployer,
+    /// init code and depth
+    #[arg(long)]
+    resume: Option<String>,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// Storage mining result JSON produced by the `storage` command
+    #[arg(short, long)]
+    input: String,
+
+    /// Output path (default: contracts/WorstCaseERC20.sol or .hex depending on --format)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Generate Solidity source or natively assembled initcode
+    #[arg(long, value_enum, default_value_t = GenerateFormat::Solidity)]
+    format: GenerateFormat,
+
+    /// How the generated Solidity contract writes the mined slots
+    #[arg(long, value_enum, default_value_t = ContractMode::Sstore)]
+    contract_mode: ContractMode,
+
+    /// Runtime deployed by the generated initcode
+    #[arg(long, value_enum, default_value_t = RuntimeKind::Attack)]
+    runtime: RuntimeKind,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Storage, forest, CREATE2, CREATE, account branch or binary tree stem result JSON to
+    /// verify
+    input: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct GenesisArgs {
+    /// CREATE2, CREATE or account branch result JSON whose accounts are allocated, or a
+    /// storage or forest result placed as a single contract at --address
+    input: String,
+
+    /// Output path of the genesis (or bare alloc) JSON or state dump
+    #[arg(short, long, default_value = GENESIS_PATH)]
+    output: String,
+
+    /// Write a genesis alloc or a state dump
+    #[arg(long, value_enum, default_value_t = GenesisFormat::Alloc)]
+    format: GenesisFormat,
+
+    /// Genesis file the accounts are merged into; without one only an alloc is written
+    #[arg(long)]
+    base: Option<String>,
+
+    /// Balance of every auxiliary account and CREATE deployer in wei (decimal or 0x-prefixed
+    /// hex); EIP-161 drops accounts without one
+    #[arg(long, default_value = "1", value_parser = layout::parse_word)]
+    balance: [u8; 32],
+
+    /// Storage or forest result the CREATE2 contracts were generated from; they are then
+    /// pre-deployed with its runtime code and storage instead of left to the factory
+    #[arg(long)]
+    predeploy: Option<String>,
+
+    /// Runtime of the pre-deployed contracts, or of the storage contract without --code
+    #[arg(long, value_enum, default_value_t = RuntimeKind::Attack)]
+    runtime: RuntimeKind,
+
+    /// Address the contract holding a storage or forest result's branch is placed at
+    #[arg(long, value_parser = parse_address)]
+    address: Option<[u8; 20]>,
+
+    /// Hex file with the runtime bytecode of the storage contract (default: the --runtime code
+    /// generated for the branch)
+    #[arg(long)]
+    code: Option<String>,
+
+    /// Value stored in every mined slot of the storage contract (decimal or 0x-prefixed hex)
+    #[arg(long, default_value = "1", value_parser = layout::parse_word)]
+    value: [u8; 32],
+}
+
+#[derive(clap::Args, Debug)]
+struct FundArgs {
+    /// CREATE2 result JSON whose auxiliary accounts are funded
+    input: String,
+
+    /// Private key of the funding account (0x-prefixed hex)
+    #[arg(long, value_parser = keys::parse_secret)]
+    key: secp256k1::SecretKey,
+
+    /// Chain id the transactions are signed for
+    #[arg(long)]
+    chain_id: u64,
+
+    /// Wei sent to each auxiliary account
+    #[arg(long, default_value_t = 1)]
+    value: u128,
+
+    #[command(flatten)]
+    signing: SigningArgs,
+
+    /// Output path of the raw transactions, one hex line each
+    #[arg(short, long, default_value = FUNDING_PATH)]
+    output: String,
+}
+
+/// Nonces, fees and batching of signed transactions, shared by every command that writes them
+#[derive(clap::Args, Debug)]
+struct SigningArgs {
+    /// Nonce of the signing account's first transaction
+    #[arg(long, default_value_t = 0)]
+    nonce: u64,
+
+    /// Max fee per gas in wei
+    #[arg(long, default_value_t = 2_000_000_000)]
+    max_fee: u128,
+
+    /// Max priority fee per gas in wei
+    #[arg(long, default_value_t = 1_000_000_000)]
+    priority_fee: u128,
+
+    /// Split the transactions into batches that each fit this block gas limit, written to
+    /// numbered files next to --output
+    #[arg(long)]
+    block_gas_limit: Option<u64>,
+}
+
+impl SigningArgs {
+    fn fees(&self) -> Result<transactions::Fees, String> {
+        if self.priority_fee > self.max_fee {
+            return Err("--priority-fee must not exceed --max-fee".to_string());
+        }
+        Ok(transactions::Fees {
+            max_fee_per_gas: self.max_fee,
+            max_priority_fee_per_gas: self.priority_fee,
+        })
+    }
+}
+
+/// Output of the `deploy` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum DeployFormat {
+    /// JSON listing the factory call of every contract
+    Json,
+    /// Raw signed transactions calling the factory, one hex line each
+    Transactions,
+}
+
+#[derive(clap::Args, Debug)]
+struct DeployArgs {
+    /// CREATE2 result JSON whose contracts are deployed
+    input: String,
+
+    /// Init code the contracts were mined with (.hex, .bin, .sol or raw bytecode)
+    #[arg(long)]
+    init_code: String,
+
+    /// Factory called (default: the result's deployer, which must be a known factory)
+    #[arg(long, value_enum)]
+    factory: Option<factories::Factory>,
+
+    /// Write the calls as JSON or as signed transactions
+    #[arg(long, value_enum, default_value_t = DeployFormat::Json)]
+    format: DeployFormat,
+
+    /// Account calling the factory, which CreateX binds caller-prefixed salts to
+    /// (default: the address of --key, or the caller the result was mined for)
+    #[arg(long, value_parser = parse_address)]
+    caller: Option<[u8; 20]>,
+
+    /// Private key signing the deployment transactions (0x-prefixed hex)
+    #[arg(long, value_parser = keys::parse_secret, required_if_eq("format", "transactions"))]
+    key: Option<secp256k1::SecretKey>,
+
+    /// Chain id the transactions are signed for, which CreateX also binds some salts to
+    /// (default: the chain id the result was mined for)
+    #[arg(long, required_if_eq("format", "transactions"))]
+    chain_id: Option<u64>,
+
+    /// Gas limit of each deployment transaction
+    #[arg(long, required_if_eq("format", "transactions"))]
+    gas_limit: Option<u64>,
+
+    #[command(flatten)]
+    signing: SigningArgs,
+
+    /// Output path (default: deployments.json, or deployment_transactions.txt for transactions)
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct StateTestArgs {
+    /// Storage or forest result (attacked through its deepest slot) or CREATE2 result (whose
+    /// accounts are all read)
+    input: String,
+
+    /// Output path of the fixture
+    #[arg(short, long, default_value = STATE_TEST_PATH)]
+    output: String,
+
+    /// Fork the expected post-state is given for
+    #[arg(long, default_value = "Cancun")]
+    fork: String,
+
+    /// Gas limit of the transaction and the block
+    #[arg(long, default_value_t = 30_000_000)]
+    gas_limit: u64,
+
+    /// Address of the contract holding a storage or forest result's branch
+    #[arg(long, default_value = "0x000000000000000000000000000000000000dead", value_parser = parse_address)]
+    address: [u8; 20],
+
+    /// Runtime of the storage contract, or of the CREATE2 contracts with --predeploy
+    #[arg(long, value_enum, default_value_t = RuntimeKind::Attack)]
+    runtime: RuntimeKind,
+
+    /// Value the attack transaction SSTOREs into the deepest slot (0 deletes it)
+    #[arg(long, default_value = "2", value_parser = layout::parse_word)]
+    value: [u8; 32],
+
+    /// Balance of every auxiliary account of a CREATE2 result in wei
+    #[arg(long, default_value = "1", value_parser = layout::parse_word)]
+    balance: [u8; 32],
+
+    /// Storage or forest result the CREATE2 contracts are pre-deployed with (see `genesis`)
+    #[arg(long)]
+    predeploy: Option<String>,
 }
 
 fn main() {
     // Initialize logger
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let args = Args::parse();
+    let cli = Cli::parse();
 
-    info!("Starting mining for depth: {}", args.depth);
+    match cli.command {
+        Commands::Storage(args) => run_storage(args),
+        Commands::Create2(args) => run_create2(args),
+        Commands::Create(args) => run_create(args),
+        Commands::Accounts(args) => run_accounts(args),
+        Commands::Stems(args) => run_stems(args),
+        Commands::Generate(args) => run_generate(args),
+        Commands::Verify(args) => run_verify(args),
+        Commands::Genesis(args) => run_genesis(args),
+        Commands::Fund(args) => run_fund(args),
+        Commands::Deploy(args) => run_deploy(args),
+        Commands::StateTest(args) => run_state_test(args),
+    }
+}
 
-    #[cfg(feature = "cuda")]
-    {
-        if args.cuda && cuda_miner::cuda_available() {
-            info!("Using CUDA acceleration");
-        } else if args.cuda {
-            info!("CUDA requested but not available, falling back to CPU");
-            info!("Using {} CPU threads", args.threads);
+fn run_storage(args: StorageArgs) {
+    let depth = args.depth as usize;
+
+    info!("Starting storage mining for depth: {depth}");
+    log_backend(args.threads, args.cuda);
+
+    let layout = args.layout.slot_layout();
+    if args.branches > 1 || args.fan_out {
+        run_forest(&args, &layout);
+        return;
+    }
+
+    let mut anchor = args
+        .snapshot
+        .as_deref()
+        .map(|path| load_snapshot(path, SnapshotKind::Storage, depth));
+
+    let (resumed, previous_time) = match args.resume.as_deref() {
+        Some(path) => {
+            let result = storage_miner::load_checkpoint(path, depth, &layout, args.exact)
+                .unwrap_or_else(|e| {
+                    error!("Cannot resume from {path}: {e}");
+                    process::exit(1);
+                });
+
+            // The checkpoint remembers its snapshot anchor; a --snapshot given again must agree
+            let resumed_anchor = result
+                .snapshot
+                .as_ref()
+                .map(|info| info.to_anchor().expect("checked by load_checkpoint"));
+            if anchor.is_some()
+                && anchor.as_ref().map(|a| a.key) != resumed_anchor.as_ref().map(|a| a.key)
+            {
+                error!("Cannot resume from {path}: it was mined below a different snapshot path");
+                process::exit(1);
+            }
+            anchor = anchor.or(resumed_anchor);
+
+            let branch = result.to_branch().expect("checked by load_checkpoint");
+            (branch, result.total_time)
+        }
+        None => (Vec::new(), 0.0),
+    };
+
+    let checkpoint_path = checkpoint::path_for(&args.output);
+    let start_time = Instant::now();
+
+    // Mine for the deep branch (storage)
+    let branch = storage_miner::mine_deep_branch(
+        depth,
+        args.threads,
+        args.cuda,
+        &layout,
+        anchor.as_ref(),
+        args.exact,
+        resumed,
+        Some(&checkpoint_path),
+    );
+
+    let elapsed = previous_time + start_time.elapsed().as_secs_f64();
+
+    // Output results
+    storage_miner::print_results(&branch, elapsed, &layout, anchor.as_ref());
+    storage_miner::write_results(
+        &branch,
+        elapsed,
+        &layout,
+        anchor.as_ref(),
+        args.exact,
+        &args.output,
+    );
+    checkpoint::remove(&checkpoint_path);
+
+    // Generate contract with mined storage keys
+    storage_miner::generate_contract(&branch, &layout, args.contract_mode, &args.contract_output);
+}
+
+/// Mine several branches (and with --fan-out their siblings) into one contract
+fn run_forest(args: &StorageArgs, layout: &SlotLayout) {
+    let depth = args.depth as usize;
+    info!(
+        "Mining a forest of {} branches{}",
+        args.branches,
+        if args.fan_out {
+            " with full fan-out"
         } else {
-            info!("Using {} CPU threads", args.threads);
+            ""
         }
+    );
+
+    let start_time = Instant::now();
+    let forest = forest::mine_forest(
+        depth,
+        args.branches as usize,
+        args.fan_out,
+        args.exact,
+        args.threads,
+        args.cuda,
+        layout,
+    );
+    let elapsed = start_time.elapsed().as_secs_f64();
+
+    forest::print_results(&forest, elapsed);
+    forest::write_results(
+        &forest,
+        depth,
+        elapsed,
+        args.fan_out,
+        args.exact,
+        layout,
+        &args.output,
+    );
+    storage_miner::generate_contract(
+        &forest.all_slots(),
+        layout,
+        args.contract_mode,
+        &args.contract_output,
+    );
+}
+
+fn run_create2(args: Create2Args) {
+    info!("Starting CREATE2 mining for depth: {}", args.depth);
+    log_backend(args.threads, false);
+
+    // Parse deployer address
+    let deployer = if let Some(deployer_str) = args.deployer {
+        parse_address(&deployer_str).expect("Invalid deployer address")
+    } else {
+        [0u8; 20] // Default to zero address
+    };
+
+    let salts = hex::decode(args.salt_prefix.trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid salt prefix: {e}"))
+        .and_then(|prefix| match args.contract_nibbles {
+            0 => SaltRange::new(prefix, args.salt_start, args.num_contracts),
+            _ => SaltRange::unbounded(prefix, args.salt_start),
+        })
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            process::exit(1);
+        });
+
+    // CreateX hashes or binds every salt before CREATE2, so contracts are mined on that salt
+    let guard = SaltGuard::for_deployer(&deployer, args.caller, args.chain_id);
+    if guard == SaltGuard::Plain && (args.caller.is_some() || args.chain_id.is_some()) {
+        error!("--caller and --chain-id only apply to the CreateX deployer");
+        process::exit(1);
+    }
+    // The counter never reaches the bytes the guard inspects, so one salt checks the range
+    if let Err(e) = guard.apply(&salts.salt_for_counter(args.salt_start)) {
+        error!("{e}");
+        process::exit(1);
     }
 
-    #[cfg(not(feature = "cuda"))]
-    {
-        if args.cuda {
-            info!("CUDA support not compiled. Rebuild with --features cuda");
+    let contract_target = args.contract_target.as_deref().map(parse_target);
+
+    // Load or generate init code
+    let init_code = if let Some(init_code_path) = args.init_code {
+        load_init_code(&init_code_path)
+    } else if args.resume.is_some() {
+        // A freshly generated contract has a different init code hash than the checkpoint
+        error!(
+            "--resume needs the original --init-code (auto-generated contracts are saved to {GENERATED_CONTRACT_PATH} or {GENERATED_INITCODE_PATH})"
+        );
+        process::exit(1);
+    } else if args.depth > 0 {
+        // No init code provided but depth specified - generate and compile a contract with the specified depth
+        info!(
+            "No init code provided. Generating contract with depth {}...",
+            args.depth
+        );
+
+        // First, mine storage slots for the contract
+        let layout = args.layout.slot_layout();
+        let branch = storage_miner::mine_deep_branch(
+            args.depth,
+            args.threads,
+            false,
+            &layout,
+            None,
+            false,
+            Vec::new(),
+            None,
+        );
+
+        match args.compiler {
+            Compiler::Native => {
+                info!("Assembling init code with {:?} runtime", args.runtime);
+                let initcode =
+                    bytecode::initcode(&branch, args.runtime, &layout).unwrap_or_else(|e| {
+                        error!("{e}");
+                        process::exit(1);
+                    });
+                // Keep the init code so an interrupted run can be resumed with --init-code
+                match save_initcode(GENERATED_INITCODE_PATH, &initcode) {
+                    Ok(()) => info!("Init code saved to: {GENERATED_INITCODE_PATH}"),
+                    Err(e) => error!("Failed to write initcode: {e}"),
+                }
+                initcode
+            }
+            Compiler::Solc => {
+                // Generate the contract
+                storage_miner::generate_contract(
+                    &branch,
+                    &layout,
+                    args.contract_mode,
+                    GENERATED_CONTRACT_PATH,
+                );
+
+                // Compile the generated contract
+                info!("Compiling generated contract: {}", GENERATED_CONTRACT_PATH);
+                compile_solidity_to_bytecode(GENERATED_CONTRACT_PATH)
+                    .expect("Failed to compile generated contract")
+            }
+        }
+    } else {
+        error!(
+            "For CREATE2 mining, either provide --init-code or specify --depth to auto-generate a contract"
+        );
+        process::exit(1);
+    };
+
+    let resumed = args.resume.as_deref().map(|path| {
+        let init_code_hash = account_miner::keccak256(&init_code);
+        account_miner::load_checkpoint(
+            path,
+            &deployer,
+            &salts,
+            &guard,
+            args.contract_nibbles,
+            contract_target.as_ref(),
+            args.exact,
+            &init_code_hash,
+            args.depth,
+            args.num_contracts as usize,
+        )
+        .and_then(|result| {
+            // Mixing keyless and keyed auxiliaries would leave part of the run unusable
+            let has_auxiliaries = result
+                .contracts
+                .iter()
+                .any(|contract| !contract.auxiliary_accounts.is_empty());
+            if has_auxiliaries && result.has_auxiliary_keys() != args.eoa_keys {
+                Err("checkpoint and --eoa-keys disagree on mining EOA keys".to_string())
+            } else {
+                Ok(result)
+            }
+        })
+        .unwrap_or_else(|e| {
+            error!("Cannot resume from {path}: {e}");
+            process::exit(1);
+        })
+    });
+
+    account_miner::mine_create2_accounts(
+        deployer,
+        &salts,
+        &guard,
+        args.contract_nibbles,
+        contract_target,
+        args.eoa_keys,
+        args.exact,
+        args.batch,
+        args.num_contracts as usize,
+        args.depth,
+        args.threads,
+        &init_code,
+        &args.output,
+        resumed,
+    );
+}
+
+fn run_create(args: CreateArgs) {
+    info!("Starting CREATE mining for depth: {}", args.depth);
+    log_backend(args.threads, false);
+
+    let deployers = match (&args.deployer, args.deployer_nibbles) {
+        (Some(deployer), _) => CreateDeployers::Nonces {
+            deployer: parse_address(deployer).unwrap_or_else(|e| {
+                error!("Invalid deployer address {deployer}: {e}");
+                process::exit(1);
+            }),
+            first_nonce: args.nonce_start,
+        },
+        (None, nibbles) => CreateDeployers::Mined {
+            nibbles: nibbles.expect("required by clap"),
+            target: args.contract_target.as_deref().map(parse_target),
+        },
+    };
+
+    account_miner::mine_create_accounts(
+        &deployers,
+        args.num_contracts as usize,
+        args.depth,
+        args.eoa_keys,
+        args.exact,
+        args.batch,
+        args.threads,
+        &args.output,
+    );
+}
+
+/// Parse a hashed account key, or an address that is hashed into one, exiting on failure
+fn parse_target(target: &str) -> [u8; 32] {
+    parse_hex_array::<32>(target)
+        .or_else(|_| parse_address(target).map(|address| account_miner::keccak256(&address)))
+        .unwrap_or_else(|e| {
+            error!("Invalid contract target {target}: {e}");
+            process::exit(1);
+        })
+}
+
+fn run_accounts(args: AccountsArgs) {
+    info!("Starting account branch mining for depth: {}", args.depth);
+    log_backend(args.threads, false);
+
+    let anchor = args
+        .snapshot
+        .as_deref()
+        .map(|path| load_snapshot(path, SnapshotKind::Accounts, args.depth as usize));
+
+    account_miner::mine_account_branch(
+        args.depth as usize,
+        args.threads,
+        anchor.as_ref(),
+        args.exact,
+        &args.output,
+    );
+}
+
+fn run_stems(args: StemsArgs) {
+    let depth = args.depth as usize;
+    info!("Starting binary tree stem mining for depth: {depth}");
+    log_backend(args.threads, false);
+
+    let source = match (args.kind, args.contract) {
+        (StemKind::Storage, Some(contract)) => StemSource::Storage {
+            contract,
+            layout: args.layout.slot_layout(),
+        },
+        (StemKind::Storage, None) => {
+            error!("Storage stems need the --contract whose storage holds them");
+            process::exit(1);
         }
-        info!("Using {} CPU threads", args.threads);
+        (StemKind::Account, _) => StemSource::Account,
+    };
+
+    let start = Instant::now();
+    let branch = binary_tree::mine_stem_branch(&source, depth, args.threads, args.exact);
+    let result = binary_tree::StemBranchResult::from_branch(
+        &source,
+        &branch,
+        start.elapsed().as_secs_f64(),
+        args.exact,
+    );
+
+    binary_tree::print_results(&result);
+    binary_tree::write_results(&result, &args.output);
+}
+
+/// Load snapshot keys and pick the deepest existing path, exiting on failure or when `depth`
+/// levels below it would need more than the 64 nibbles of a key
+fn load_snapshot(path: &str, kind: SnapshotKind, depth: usize) -> SnapshotAnchor {
+    let keys = snapshot::load_keys(path, kind).unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
+
+    let Some(anchor) = snapshot::deepest_prefix(&keys) else {
+        error!("Snapshot {path} contains no keys");
+        process::exit(1);
+    };
+
+    info!(
+        "Loaded {} snapshot keys from {path}; deepest existing path shares {} nibbles",
+        anchor.num_keys, anchor.shared_nibbles
+    );
+    let deepest_nibbles = anchor.required_nibbles() + depth - 1;
+    if deepest_nibbles > 64 {
+        error!(
+            "{depth} levels below the snapshot would need {deepest_nibbles} shared nibbles, more than a key holds"
+        );
+        process::exit(1);
     }
+    anchor
+}
 
-    // Mine CREATE2 accounts if requested
-    if let Some(num_contracts) = args.num_contracts {
-        // Parse deployer address
-        let deployer = if let Some(deployer_str) = args.deployer {
-            parse_address(&deployer_str).expect("Invalid deployer address")
-        } else {
-            [0u8; 20] // Default to zero address
-        };
+/// Load the mined slots of a storage or forest result, which must hold at least one
+fn load_storage_branch(
+    input: &str,
+) -> Result<(Vec<storage_miner::StorageSlot>, SlotLayout), String> {
+    // Forest results carry several branches; the contract writes all of their slots
+    let (branch, layout) = storage_miner::load_results(input)
+        .and_then(|result| Ok((result.to_branch()?, result.layout.to_layout()?)))
+        .or_else(|e| {
+            forest::load_results(input)
+                .map_err(|_| e)
+                .and_then(|result| {
+                    Ok((result.to_forest()?.all_slots(), result.layout.to_layout()?))
+                })
+        })?;
+
+    if branch.is_empty() {
+        return Err(format!("{input} contains no mined storage slots"));
+    }
+    Ok((branch, layout))
+}
 
-        // Load or generate init code
-        let init_code = if let Some(init_code_path) = args.init_code {
-            // Check if it's a .sol file or a hex file
-            if init_code_path.ends_with(".sol") {
-                // Compile the Solidity file to get bytecode
-                info!("Compiling Solidity contract: {}", init_code_path);
-                compile_solidity_to_bytecode(&init_code_path)
-                    .expect("Failed to compile Solidity contract")
-            } else if init_code_path.ends_with(".hex") || init_code_path.ends_with(".bin") {
-                // Read hex bytecode from file
-                info!("Loading bytecode from: {}", init_code_path);
-                let hex_content = std::fs::read_to_string(&init_code_path)
-                    .expect("Failed to read bytecode file");
-                let hex_content = hex_content.trim();
-                let hex_content = hex_content.strip_prefix("0x").unwrap_or(hex_content);
-                hex::decode(hex_content).expect("Invalid hex in bytecode file")
+fn run_generate(args: GenerateArgs) {
+    let (branch, layout) = load_storage_branch(&args.input).unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
+
+    match args.format {
+        GenerateFormat::Solidity => {
+            let output = args.output.as_deref().unwrap_or(GENERATED_CONTRACT_PATH);
+            storage_miner::generate_contract(&branch, &layout, args.contract_mode, output);
+        }
+        GenerateFormat::Initcode => {
+            let output = args.output.as_deref().unwrap_or(GENERATED_INITCODE_PATH);
+            let initcode = bytecode::initcode(&branch, args.runtime, &layout).unwrap_or_else(|e| {
+                error!("{e}");
+                process::exit(1);
+            });
+
+            if let Err(e) = save_initcode(output, &initcode) {
+                error!("Failed to write initcode: {e}");
+                process::exit(1);
+            }
+            info!(
+                "Initcode ({} bytes, {:?} runtime) saved to: {output}",
+                initcode.len(),
+                args.runtime
+            );
+            info!(
+                "Init code hash: 0x{}",
+                hex::encode(account_miner::keccak256(&initcode))
+            );
+        }
+    }
+}
+
+fn run_genesis(args: GenesisArgs) {
+    let input = &args.input;
+    if args.format == GenesisFormat::Dump && args.base.is_some() {
+        error!("--base only applies to --format alloc");
+        process::exit(1);
+    }
+
+    let create2 = account_miner::load_results(input);
+    let alloc = if let Ok(result) = create2 {
+        let predeploy = args.predeploy.as_deref().map(|path| {
+            let (branch, layout) = load_storage_branch(path).unwrap_or_else(|e| {
+                error!("{e}");
+                process::exit(1);
+            });
+            let deployer = parse_hex_array::<20>(&result.deployer).unwrap_or_else(|e| {
+                error!("{e}");
+                process::exit(1);
+            });
+
+            // The addresses follow from the init code hash, so code from another branch or
+            // runtime would sit at addresses CREATE2 never gives it
+            match bytecode::initcode(&branch, args.runtime, &layout) {
+                Ok(initcode) => {
+                    let init_code_hash = account_miner::keccak256(&initcode);
+                    if format!("0x{}", hex::encode(init_code_hash)) != result.init_code_hash {
+                        warn!(
+                            "{path} with the {:?} runtime does not reproduce init code hash {}; the contracts get its code anyway",
+                            args.runtime, result.init_code_hash
+                        );
+                    }
+                }
+                Err(e) => warn!(
+                    "{path} cannot be deployed by initcode ({e}); the contracts get its code anyway"
+                ),
+            }
+            genesis::Predeploy::from_branch(&branch, args.runtime, &layout, &deployer)
+                .unwrap_or_else(|e| {
+                    error!("{e}");
+                    process::exit(1);
+                })
+        });
+        info!(
+            "Allocating CREATE2 result ({} contracts, {})",
+            result.num_contracts,
+            if predeploy.is_some() {
+                "pre-deployed"
             } else {
-                // Assume it's raw bytecode
-                std::fs::read(&init_code_path).expect("Failed to read init code file")
+                "left to the factory"
             }
-        } else if args.depth > 0 {
-            // No init code provided but depth specified - generate and compile a contract with the specified depth
-            info!("No init code provided. Generating contract with depth {}...", args.depth);
+        );
+        genesis::create2_alloc(&result, &args.balance, predeploy.as_ref())
+    } else if let (Some(_), Err(e)) = (&args.predeploy, create2) {
+        Err(format!("--predeploy needs a CREATE2 result: {e}"))
+    } else if let Ok(result) = account_miner::load_create_results(input) {
+        info!(
+            "Allocating CREATE result ({} contracts)",
+            result.num_contracts
+        );
+        genesis::create_alloc(&result, &args.balance)
+    } else if let Ok(result) = account_miner::load_branch_results(input) {
+        info!("Allocating account branch ({} levels)", result.depth);
+        genesis::branch_alloc(&result, &args.balance)
+    } else {
+        match load_storage_branch(input) {
+            Ok((branch, layout)) => storage_contract_alloc(&args, &branch, &layout),
+            Err(e) => Err(format!(
+                "{input} is not a CREATE2, CREATE, account branch, storage or forest result: {e}"
+            )),
+        }
+    };
 
-            // First, mine storage slots for the contract
-            let branch = storage_miner::mine_deep_branch(args.depth, args.threads, false);
+    let outcome = alloc.and_then(|alloc| {
+        match args.format {
+            GenesisFormat::Alloc => {
+                genesis::write_genesis(&args.output, args.base.as_deref(), &alloc)?
+            }
+            GenesisFormat::Dump => genesis::write_state_dump(&args.output, &alloc)?,
+        }
+        Ok(alloc.len())
+    });
+    match outcome {
+        Ok(accounts) => info!("{accounts} accounts allocated in: {}", args.output),
+        Err(e) => {
+            error!("Genesis export failed: {e}");
+            process::exit(1);
+        }
+    }
+}
 
-            // Generate the contract
-            storage_miner::generate_contract(&branch);
+fn run_fund(args: FundArgs) {
+    let result = account_miner::load_results(&args.input).unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
+    let recipients = result
+        .contracts
+        .iter()
+        .flat_map(|contract| &contract.auxiliary_accounts)
+        .map(|address| parse_address(address))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            process::exit(1);
+        });
+    let fees = args.signing.fees().unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
 
-            // Compile the generated contract
-            let contract_path = "contracts/WorstCaseERC20.sol";
-            info!("Compiling generated contract: {}", contract_path);
-            compile_solidity_to_bytecode(contract_path)
-                .expect("Failed to compile generated contract")
-        } else {
-            panic!("For CREATE2 mining, either provide --init-code or specify --depth to auto-generate a contract");
-        };
+    let secp = secp256k1::Secp256k1::signing_only();
+    let funding = transactions::funding_transactions(
+        &recipients,
+        args.chain_id,
+        args.signing.nonce,
+        fees,
+        args.value,
+    );
+    let max_cost = funding.iter().fold(0u128, |total, tx| {
+        total.saturating_add(
+            tx.value
+                .saturating_add(u128::from(tx.gas_limit).saturating_mul(fees.max_fee_per_gas)),
+        )
+    });
+    info!(
+        "Signing {} transfers from 0x{} (nonces {}..{}, at most {max_cost} wei)",
+        funding.len(),
+        hex::encode(keys::address_of(&secp, &args.key)),
+        args.signing.nonce,
+        args.signing.nonce + funding.len() as u64
+    );
 
-        account_miner::mine_create2_accounts(
-            deployer,
-            num_contracts,
-            args.depth,
-            args.threads,
-            &init_code,
-            &args.accounts_output,
+    if let Err(e) = write_signed(&funding, &args.key, &args.signing, &args.output) {
+        error!("Writing funding transactions failed: {e}");
+        process::exit(1);
+    }
+}
+
+fn run_deploy(args: DeployArgs) {
+    let result = account_miner::load_results(&args.input).unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
+    let factory = args.factory.or_else(|| {
+        parse_address(&result.deployer)
+            .ok()
+            .and_then(|deployer| factories::Factory::at(&deployer))
+    });
+    let Some(factory) = factory else {
+        error!(
+            "Deployer {} is not a known factory; pass --factory to choose one",
+            result.deployer
         );
+        process::exit(1);
+    };
 
-        // Exit after CREATE2 mining - don't continue to storage mining
-        return;
+    let secp = secp256k1::Secp256k1::signing_only();
+    let mined_guard = result.salt_guard().unwrap_or_else(|e| {
+        error!("{e}");
+        process::exit(1);
+    });
+    let caller = args
+        .caller
+        .or_else(|| args.key.map(|key| keys::address_of(&secp, &key)))
+        .or(mined_guard.caller());
+    let chain_id = args.chain_id.or(mined_guard.chain_id());
+    let init_code = load_init_code(&args.init_code);
+    let plan = factories::deployments(&result, &init_code, factory, caller.as_ref(), chain_id)
+        .unwrap_or_else(|e| {
+            error!("Deployment check failed: {e}");
+            process::exit(1);
+        });
+    info!(
+        "{} contracts deploy to their mined addresses through {factory} at {}",
+        plan.deployments.len(),
+        plan.factory_address
+    );
+
+    let outcome = match args.format {
+        DeployFormat::Json => {
+            let output = args.output.as_deref().unwrap_or(DEPLOYMENTS_PATH);
+            serde_json::to_string_pretty(&plan)
+                .map_err(|e| format!("Failed to serialize: {e}"))
+                .and_then(|json| {
+                    std::fs::write(output, json)
+                        .map_err(|e| format!("Failed to write {output}: {e}"))
+                })
+                .map(|()| info!("Deployments saved to: {output}"))
+        }
+        DeployFormat::Transactions => {
+            // clap requires the key, chain id and gas limit for this format
+            let (Some(key), Some(chain_id), Some(gas_limit)) =
+                (args.key, args.chain_id, args.gas_limit)
+            else {
+                unreachable!("required by --format transactions");
+            };
+            let output = args
+                .output
+                .as_deref()
+                .unwrap_or(DEPLOYMENT_TRANSACTIONS_PATH);
+            args.signing.fees().and_then(|fees| {
+                let deployments: Vec<_> = plan
+                    .deployments
+                    .into_iter()
+                    .zip(args.signing.nonce..)
+                    .map(|(deployment, nonce)| transactions::Eip1559Transaction {
+                        chain_id,
+                        nonce,
+                        fees,
+                        gas_limit,
+                        to: factory.address(),
+                        value: 0,
+                        data: deployment.data,
+                    })
+                    .collect();
+                write_signed(&deployments, &key, &args.signing, output)
+            })
+        }
+    };
+    if let Err(e) = outcome {
+        error!("Writing deployments failed: {e}");
+        process::exit(1);
     }
+}
 
-    let start_time = Instant::now();
+fn run_state_test(args: StateTestArgs) {
+    let input = &args.input;
+    let test = if let Ok(result) = account_miner::load_results(input) {
+        let predeploy = args.predeploy.as_deref().map(|path| {
+            let (branch, layout) = load_storage_branch(path).unwrap_or_else(|e| {
+                error!("{e}");
+                process::exit(1);
+            });
+            let deployer = parse_address(&result.deployer).unwrap_or_else(|e| {
+                error!("{e}");
+                process::exit(1);
+            });
+            genesis::Predeploy::from_branch(&branch, args.runtime, &layout, &deployer)
+                .unwrap_or_else(|e| {
+                    error!("{e}");
+                    process::exit(1);
+                })
+        });
+        let name = format!("worst_case_accounts_depth{}", result.target_depth);
+        info!(
+            "Building {name}: reading {} contracts and their auxiliary accounts",
+            result.num_contracts
+        );
+        statetest::create2_test(
+            &result,
+            &args.balance,
+            predeploy.as_ref(),
+            args.gas_limit,
+            &args.fork,
+        )
+        .map(|test| (name, test))
+    } else {
+        match load_storage_branch(input) {
+            Ok((branch, layout)) => {
+                let name = format!("worst_case_storage_{}_slots", branch.len());
+                info!(
+                    "Building {name}: attack on the deepest of {} slots at 0x{}",
+                    branch.len(),
+                    hex::encode(args.address)
+                );
+                statetest::storage_test(
+                    &branch,
+                    args.runtime,
+                    &layout,
+                    &args.address,
+                    &args.value,
+                    args.gas_limit,
+                    &args.fork,
+                )
+                .map(|test| (name, test))
+            }
+            Err(e) => Err(format!(
+                "{input} is not a CREATE2, storage or forest result: {e}"
+            )),
+        }
+    };
 
-    // Mine for the deep branch (storage)
-    let branch = storage_miner::mine_deep_branch(args.depth, args.threads, args.cuda);
+    let outcome = test.and_then(|(name, test)| {
+        statetest::write_fixture(&args.output, &name, &test)?;
+        Ok(test.post[&args.fork][0].hash.clone())
+    });
+    match outcome {
+        Ok(root) => info!("Fixture (post-state root {root}) saved to: {}", args.output),
+        Err(e) => {
+            error!("State test export failed: {e}");
+            process::exit(1);
+        }
+    }
+}
 
-    let elapsed = start_time.elapsed();
+/// Sign transactions and write them to `output`, or to numbered batch files when a block
+/// gas limit is given
+fn write_signed(
+    transactions: &[transactions::Eip1559Transaction],
+    key: &secp256k1::SecretKey,
+    signing: &SigningArgs,
+    output: &str,
+) -> Result<(), String> {
+    let secp = secp256k1::Secp256k1::signing_only();
+    let batches = match signing.block_gas_limit {
+        Some(block_gas_limit) => transactions::batches(transactions, block_gas_limit)?,
+        None => vec![transactions],
+    };
 
-    // Output results
-    storage_miner::print_results(&branch, elapsed.as_secs_f64());
+    for (i, batch) in batches.iter().enumerate() {
+        let path = match signing.block_gas_limit {
+            Some(_) => batch_path(output, i),
+            None => output.to_string(),
+        };
+        let raw: Vec<_> = batch.iter().map(|tx| tx.sign(&secp, key)).collect();
+        transactions::write_raw_transactions(&path, &raw)?;
+        info!("{} transactions written to: {path}", raw.len());
+    }
+    Ok(())
+}
 
-    // Generate contract with mined storage keys
-    storage_miner::generate_contract(&branch);
+/// Path of the `index`th batch: the number goes before the extension of `output`
+fn batch_path(output: &str, index: usize) -> String {
+    let path = std::path::Path::new(output);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => path
+            .with_file_name(format!(
+                "{}_{index:03}.{}",
+                stem.to_string_lossy(),
+                extension.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .into_owned(),
+        _ => format!("{output}_{index:03}"),
+    }
 }
 
-/// Parse hex address string to bytes
+/// Alloc of the contract holding a mined storage branch, at the address given on the command line
+fn storage_contract_alloc(
+    args: &GenesisArgs,
+    branch: &[storage_miner::StorageSlot],
+    layout: &SlotLayout,
+) -> Result<genesis::Alloc, String> {
+    let address = args
+        .address
+        .ok_or("--address is required to place a storage branch")?;
+    if args.value == [0u8; 32] {
+        return Err("--value must be nonzero: zero slots are not stored".to_string());
+    }
+
+    let code = match &args.code {
+        Some(path) => {
+            let hex_content =
+                std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+            let hex_content = hex_content.trim();
+            let hex_content = hex_content.strip_prefix("0x").unwrap_or(hex_content);
+            hex::decode(hex_content).map_err(|e| format!("Invalid hex in {path}: {e}"))?
+        }
+        None => bytecode::runtime_code(branch, args.runtime, layout)?,
+    };
+    info!(
+        "Placing {} mined slots and {} bytes of runtime code at 0x{}",
+        branch.len(),
+        code.len(),
+        hex::encode(address)
+    );
+
+    let predeploy = genesis::Predeploy::with_value(branch, code, &args.value);
+    Ok(genesis::contract_alloc(&address, &predeploy))
+}
+
+/// Load init code from a Solidity source (compiled with solc), a hex file or raw bytecode
+fn load_init_code(init_code_path: &str) -> Vec<u8> {
+    // Check if it's a .sol file or a hex file
+    if init_code_path.ends_with(".sol") {
+        // Compile the Solidity file to get bytecode
+        info!("Compiling Solidity contract: {}", init_code_path);
+        compile_solidity_to_bytecode(init_code_path).expect("Failed to compile Solidity contract")
+    } else if init_code_path.ends_with(".hex") || init_code_path.ends_with(".bin") {
+        // Read hex bytecode from file
+        info!("Loading bytecode from: {}", init_code_path);
+        let hex_content =
+            std::fs::read_to_string(init_code_path).expect("Failed to read bytecode file");
+        let hex_content = hex_content.trim();
+        let hex_content = hex_content.strip_prefix("0x").unwrap_or(hex_content);
+        hex::decode(hex_content).expect("Invalid hex in bytecode file")
+    } else {
+        // Assume it's raw bytecode
+        std::fs::read(init_code_path).expect("Failed to read init code file")
+    }
+}
+
+/// Write init code as hex, creating the parent directory if needed
+fn save_initcode(path: &str, initcode: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, hex::encode(initcode))
+}
+
+fn run_verify(args: VerifyArgs) {
+    // Every result format is tried in turn; each one is recognised by its required fields
+    let input = &args.input;
+    let outcome = if let Ok(result) = storage_miner::load_results(input) {
+        info!("Verifying storage mining result ({} levels)", result.depth);
+        storage_miner::verify_results(&result)
+    } else if let Ok(result) = account_miner::load_results(input) {
+        info!(
+            "Verifying CREATE2 mining result ({} contracts, depth {})",
+            result.num_contracts, result.target_depth
+        );
+        account_miner::verify_results(&result)
+    } else if let Ok(result) = account_miner::load_create_results(input) {
+        info!(
+            "Verifying CREATE mining result ({} contracts, depth {})",
+            result.num_contracts, result.target_depth
+        );
+        account_miner::verify_create_results(&result)
+    } else if let Ok(result) = account_miner::load_branch_results(input) {
+        info!("Verifying account branch result ({} levels)", result.depth);
+        account_miner::verify_branch_results(&result)
+    } else if let Ok(result) = binary_tree::load_results(input) {
+        info!(
+            "Verifying binary tree stem result ({} levels)",
+            result.depth
+        );
+        binary_tree::verify_results(&result)
+    } else {
+        match forest::load_results(input) {
+            Ok(result) => {
+                info!(
+                    "Verifying storage forest ({} branches, depth {})",
+                    result.branches.len(),
+                    result.depth
+                );
+                forest::verify_results(&result)
+            }
+            Err(e) => Err(format!(
+                "{input} is not a storage, CREATE2, CREATE, account branch, stem branch or forest result: {e}"
+            )),
+        }
+    };
+
+    match outcome {
+        Ok(()) => info!("Verification passed: {}", args.input),
+        Err(e) => {
+            error!("Verification failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Log which mining backend will be used
+fn log_backend(threads: usize, #[allow(unused_variables)] cuda: bool) {
+    #[cfg(feature = "cuda")]
+    {
+        if cuda && cuda_miner::cuda_available() {
+            info!("Using CUDA acceleration");
+        } else if cuda {
+            info!("CUDA requested but not available, falling back to CPU");
+            info!("Using {} CPU threads", threads);
+        } else {
+            info!("Using {} CPU threads", threads);
+        }
+    }
+
+    #[cfg(not(feature = "cuda"))]
+    {
+        if cuda {
+            info!("CUDA support not compiled. Rebuild with --features cuda");
+        }
+        info!("Using {} CPU threads", threads);
+    }
+}
+
+/// Compile a Solidity file with solc and return its creation bytecode
 fn compile_solidity_to_bytecode(sol_path: &str) -> Result<Vec<u8>, String> {
     // Run solc to compile the contract with consistent metadata settings
     let output = Command::new("solc")
-        .args(["--optimize", "--optimize-runs", "200", "--bin", "--metadata-hash", "none", sol_path])
+        .args([
+            "--optimize",
+            "--optimize-runs",
+            "200",
+            "--bin",
+            "--metadata-hash",
+            "none",
+            sol_path,
+        ])
         .output()
         .map_err(|e| format!("Failed to run solc: {}. Make sure solc is installed.", e))?;
 
@@ -180,19 +1637,31 @@ fn compile_solidity_to_bytecode(sol_path: &str) -> Result<Vec<u8>, String> {
     Err("Could not find bytecode in solc output".to_string())
 }
 
+/// Parse hex address string to bytes
 fn parse_address(hex_str: &str) -> Result<[u8; 20], String> {
+    parse_hex_array(hex_str)
+}
+
+/// Parser of nibble counts, which cannot exceed the 64 nibbles of a 32-byte key
+fn nibble_count() -> clap::builder::RangedU64ValueParser<usize> {
+    clap::builder::RangedU64ValueParser::new().range(0..=64)
+}
+
+/// Parse a 0x-prefixed (or bare) hex string into a fixed-size byte array
+pub(crate) fn parse_hex_array<const N: usize>(hex_str: &str) -> Result<[u8; N], String> {
     let hex_str = hex_str.strip_prefix("0x").unwrap_or(hex_str);
 
-    if hex_str.len() != 40 {
+    if hex_str.len() != N * 2 {
         return Err(format!(
-            "Address must be 40 hex characters, got {}",
+            "Expected {} hex characters, got {}",
+            N * 2,
             hex_str.len()
         ));
     }
 
     let bytes = hex::decode(hex_str).map_err(|e| format!("Invalid hex: {e}"))?;
 
-    let mut address = [0u8; 20];
-    address.copy_from_slice(&bytes);
-    Ok(address)
+    let mut array = [0u8; N];
+    array.copy_from_slice(&bytes);
+    Ok(array)
 }
@@ -0,0 +1,595 @@
+//! # MPT Module
+//!
+//! An in-memory Merkle Patricia Trie used to check the depth the mined keys really produce.
+//! Keys are inserted as given (mined storage keys, or keccak256 of account addresses), nodes
+//! are RLP encoded and hashed the same way clients do, and the path to any key can be
+//! inspected node by node, including the Merkle proof (witness) a stateless client needs for it.
+//!
+//! ## Key Functions
+//! - `Trie::insert`: Adds a key/value pair, splitting leaves and extensions into branches
+//! - `Trie::root_hash`: Computes the trie root
+//! - `Trie::path`: Walks the nodes from the root to a key
+//! - `Trie::report`: Summarizes the path and the size of the proof for a key
+//! - `Trie::shape`: Summarizes the whole trie (node counts, saturated branches, leaf depths)
+//! - `account_rlp`: Encodes an account as stored in the account trie
+
+use crate::account_miner::keccak256;
+use crate::rlp;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+
+/// Root of an empty trie: keccak256(rlp(""))
+pub const EMPTY_ROOT: [u8; 32] = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Code hash of an account without code: keccak256("")
+pub const EMPTY_CODE_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// Type of a trie node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Branch,
+    Extension,
+    Leaf,
+}
+
+enum Node {
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+        encoded: OnceCell<Vec<u8>>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+        encoded: OnceCell<Vec<u8>>,
+    },
+}
+
+/// A node visited on the way from the root to a key
+#[derive(Clone, Debug)]
+pub struct PathNode {
+    pub kind: NodeKind,
+    /// Length of the node's RLP encoding
+    pub size: usize,
+    /// Whether the node is shorter than 32 bytes and inlined into its parent, so that it is
+    /// not a separate proof node
+    pub embedded: bool,
+    /// Children off the path that the node refers to by hash
+    pub sibling_hashes: usize,
+}
+
+/// Verified shape of the path to a key, as written to the result JSON
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrieReport {
+    pub root: String,
+    /// Number of nodes from the root to the key's leaf, inclusive
+    pub depth: usize,
+    pub branch_nodes: usize,
+    pub node_types: Vec<NodeKind>,
+    /// Size of the Merkle proof for the key (absent in results written before proofs were
+    /// reported)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ProofReport>,
+}
+
+/// Size of the Merkle proof for a key: the nodes an `eth_getProof` style witness holds
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofReport {
+    /// RLP size of each proof node, from the root down (embedded nodes count towards their
+    /// parent)
+    pub node_sizes: Vec<usize>,
+    /// Total bytes of the proof nodes
+    pub total_bytes: usize,
+    /// 32-byte hashes of siblings off the path carried by the proof nodes
+    pub sibling_hashes: usize,
+}
+
+impl ProofReport {
+    fn from_path(path: &[PathNode]) -> Self {
+        let node_sizes: Vec<usize> = path
+            .iter()
+            .filter(|node| !node.embedded)
+            .map(|node| node.size)
+            .collect();
+        ProofReport {
+            total_bytes: node_sizes.iter().sum(),
+            sibling_hashes: path.iter().map(|node| node.sibling_hashes).sum(),
+            node_sizes,
+        }
+    }
+
+    /// Human readable summary, e.g. `3 nodes, 1130 bytes, 30 sibling hashes (532 + 532 + 66)`
+    pub fn describe(&self) -> String {
+        let sizes: Vec<String> = self.node_sizes.iter().map(usize::to_string).collect();
+        format!(
+            "{} nodes, {} bytes, {} sibling hashes ({})",
+            self.node_sizes.len(),
+            self.total_bytes,
+            self.sibling_hashes,
+            sizes.join(" + ")
+        )
+    }
+}
+
+/// Aggregate shape of a whole trie, as written to the result JSON
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrieShape {
+    pub root: String,
+    pub leaves: usize,
+    pub branch_nodes: usize,
+    pub extension_nodes: usize,
+    /// Branch nodes with all 16 children occupied
+    pub full_branch_nodes: usize,
+    /// Nodes from the root to the deepest leaf, inclusive
+    pub max_depth: usize,
+    /// Number of leaves at each depth in nodes (index = depth)
+    pub leaf_depths: Vec<usize>,
+}
+
+/// Merkle Patricia Trie over byte keys
+pub struct Trie {
+    root: Node,
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie { root: Node::Empty }
+    }
+
+    /// Insert a value (stored as an RLP string in its leaf) under the given key
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        let root = std::mem::replace(&mut self.root, Node::Empty);
+        self.root = insert(root, &to_nibbles(key), value);
+    }
+
+    /// Root hash of the trie
+    pub fn root_hash(&self) -> [u8; 32] {
+        match self.root {
+            Node::Empty => EMPTY_ROOT,
+            ref root => keccak256(&encode(root)),
+        }
+    }
+
+    /// Nodes traversed from the root to the given key (empty if the key is absent)
+    pub fn path(&self, key: &[u8]) -> Vec<PathNode> {
+        let nibbles = to_nibbles(key);
+        let mut remaining = &nibbles[..];
+        let mut node = &self.root;
+        let mut path = Vec::new();
+        let visit = |node: &Node, kind, sibling_hashes| {
+            let size = encode(node).len();
+            PathNode {
+                kind,
+                size,
+                // The root is always a proof node of its own
+                embedded: !std::ptr::eq(node, &self.root) && size < 32,
+                sibling_hashes,
+            }
+        };
+
+        loop {
+            match node {
+                Node::Empty => return Vec::new(),
+                Node::Leaf {
+                    path: leaf_path, ..
+                } => {
+                    if leaf_path[..] != *remaining {
+                        return Vec::new();
+                    }
+                    path.push(visit(node, NodeKind::Leaf, 0));
+                    return path;
+                }
+                Node::Extension {
+                    path: ext_path,
+                    child,
+                    ..
+                } => {
+                    if !remaining.starts_with(ext_path) {
+                        return Vec::new();
+                    }
+                    path.push(visit(node, NodeKind::Extension, 0));
+                    remaining = &remaining[ext_path.len()..];
+                    node = child;
+                }
+                Node::Branch { children, .. } => {
+                    let next = remaining.first().map(|&nibble| nibble as usize);
+                    let sibling_hashes = children
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, child)| Some(i) != next && is_hashed(child))
+                        .count();
+                    path.push(visit(node, NodeKind::Branch, sibling_hashes));
+                    let Some((&nibble, rest)) = remaining.split_first() else {
+                        return path;
+                    };
+                    remaining = rest;
+                    node = &children[nibble as usize];
+                }
+            }
+        }
+    }
+
+    /// Count the nodes of the whole trie
+    pub fn shape(&self) -> TrieShape {
+        let mut shape = TrieShape {
+            root: format!("0x{}", hex::encode(self.root_hash())),
+            ..TrieShape::default()
+        };
+        collect_shape(&self.root, 1, &mut shape);
+        shape
+    }
+
+    /// Summarize the path to a key and the size of its proof for reporting
+    pub fn report(&self, key: &[u8]) -> TrieReport {
+        let path = self.path(key);
+        TrieReport {
+            proof: (!path.is_empty()).then(|| ProofReport::from_path(&path)),
+            root: format!("0x{}", hex::encode(self.root_hash())),
+            depth: path.len(),
+            branch_nodes: path
+                .iter()
+                .filter(|node| node.kind == NodeKind::Branch)
+                .count(),
+            node_types: path.iter().map(|node| node.kind).collect(),
+        }
+    }
+}
+
+/// Add a node and its descendants at `depth` (the root is at depth 1) to the shape
+fn collect_shape(node: &Node, depth: usize, shape: &mut TrieShape) {
+    match node {
+        Node::Empty => {}
+        Node::Leaf { .. } => {
+            shape.leaves += 1;
+            shape.max_depth = shape.max_depth.max(depth);
+            if shape.leaf_depths.len() <= depth {
+                shape.leaf_depths.resize(depth + 1, 0);
+            }
+            shape.leaf_depths[depth] += 1;
+        }
+        Node::Extension { child, .. } => {
+            shape.extension_nodes += 1;
+            collect_shape(child, depth + 1, shape);
+        }
+        Node::Branch { children, .. } => {
+            shape.branch_nodes += 1;
+            if children.iter().all(|child| !matches!(child, Node::Empty)) {
+                shape.full_branch_nodes += 1;
+            }
+            for child in children.iter() {
+                collect_shape(child, depth + 1, shape);
+            }
+        }
+    }
+}
+
+/// RLP encoding of an account: [nonce, balance, storage_root, code_hash]
+pub fn account_rlp(
+    nonce: u64,
+    balance: u128,
+    storage_root: &[u8; 32],
+    code_hash: &[u8; 32],
+) -> Vec<u8> {
+    rlp::encode_list(&[
+        rlp::encode_u64(nonce),
+        rlp::encode_u128(balance),
+        rlp::encode_bytes(storage_root),
+        rlp::encode_bytes(code_hash),
+    ])
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0F]).collect()
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn empty_children() -> Box<[Node; 16]> {
+    Box::new(std::array::from_fn(|_| Node::Empty))
+}
+
+fn new_branch() -> (Box<[Node; 16]>, Option<Vec<u8>>) {
+    (empty_children(), None)
+}
+
+/// Place a key remainder and its value directly below a branch
+fn attach(
+    children: &mut [Node; 16],
+    branch_value: &mut Option<Vec<u8>>,
+    key: &[u8],
+    value: Vec<u8>,
+) {
+    match key.split_first() {
+        None => *branch_value = Some(value),
+        Some((&nibble, rest)) => {
+            children[nibble as usize] = Node::Leaf {
+                path: rest.to_vec(),
+                value,
+            }
+        }
+    }
+}
+
+/// Wrap a node in an extension if the shared path is non-empty
+fn with_extension(path: &[u8], node: Node) -> Node {
+    if path.is_empty() {
+        node
+    } else {
+        Node::Extension {
+            path: path.to_vec(),
+            child: Box::new(node),
+            encoded: OnceCell::new(),
+        }
+    }
+}
+
+fn insert(node: Node, key: &[u8], value: Vec<u8>) -> Node {
+    match node {
+        Node::Empty => Node::Leaf {
+            path: key.to_vec(),
+            value,
+        },
+        Node::Leaf {
+            path,
+            value: old_value,
+        } => {
+            let common = common_prefix(&path, key);
+            if common == path.len() && common == key.len() {
+                return Node::Leaf { path, value };
+            }
+
+            let (mut children, mut branch_value) = new_branch();
+            attach(&mut children, &mut branch_value, &path[common..], old_value);
+            attach(&mut children, &mut branch_value, &key[common..], value);
+
+            let branch = Node::Branch {
+                children,
+                value: branch_value,
+                encoded: OnceCell::new(),
+            };
+            with_extension(&key[..common], branch)
+        }
+        Node::Extension { path, child, .. } => {
+            let common = common_prefix(&path, key);
+            if common == path.len() {
+                return Node::Extension {
+                    child: Box::new(insert(*child, &key[common..], value)),
+                    path,
+                    encoded: OnceCell::new(),
+                };
+            }
+
+            // Split the extension at the first diverging nibble
+            let (mut children, mut branch_value) = new_branch();
+            let rest = &path[common + 1..];
+            children[path[common] as usize] = with_extension(rest, *child);
+            attach(&mut children, &mut branch_value, &key[common..], value);
+
+            let branch = Node::Branch {
+                children,
+                value: branch_value,
+                encoded: OnceCell::new(),
+            };
+            with_extension(&key[..common], branch)
+        }
+        Node::Branch {
+            mut children,
+            value: branch_value,
+            ..
+        } => match key.split_first() {
+            None => Node::Branch {
+                children,
+                value: Some(value),
+                encoded: OnceCell::new(),
+            },
+            Some((&nibble, rest)) => {
+                let child = std::mem::replace(&mut children[nibble as usize], Node::Empty);
+                children[nibble as usize] = insert(child, rest, value);
+                Node::Branch {
+                    children,
+                    value: branch_value,
+                    encoded: OnceCell::new(),
+                }
+            }
+        },
+    }
+}
+
+/// Hex-prefix encoding of a nibble path
+fn hex_prefix(path: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(path.len() / 2 + 1);
+
+    let rest = if path.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | path[0]);
+        &path[1..]
+    } else {
+        out.push(flag << 4);
+        path
+    };
+    out.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    out
+}
+
+/// RLP encoding of a node
+fn encode(node: &Node) -> Vec<u8> {
+    match node {
+        Node::Empty => rlp::encode_bytes(&[]),
+        Node::Leaf { path, value } => rlp::encode_list(&[
+            rlp::encode_bytes(&hex_prefix(path, true)),
+            rlp::encode_bytes(value),
+        ]),
+        Node::Extension {
+            path,
+            child,
+            encoded,
+        } => encoded
+            .get_or_init(|| {
+                rlp::encode_list(&[
+                    rlp::encode_bytes(&hex_prefix(path, false)),
+                    reference(child),
+                ])
+            })
+            .clone(),
+        Node::Branch {
+            children,
+            value,
+            encoded,
+        } => encoded
+            .get_or_init(|| {
+                let mut items: Vec<Vec<u8>> = children.iter().map(reference).collect();
+                items.push(rlp::encode_bytes(value.as_deref().unwrap_or(&[])));
+                rlp::encode_list(&items)
+            })
+            .clone(),
+    }
+}
+
+/// How a parent refers to a child: inline if its encoding is shorter than 32 bytes, else by hash
+fn reference(node: &Node) -> Vec<u8> {
+    let encoded = encode(node);
+    if matches!(node, Node::Empty) || encoded.len() < 32 {
+        encoded
+    } else {
+        rlp::encode_bytes(&keccak256(&encoded))
+    }
+}
+
+/// Whether a parent refers to the node by its hash rather than inlining it
+fn is_hashed(node: &Node) -> bool {
+    !matches!(node, Node::Empty) && encode(node).len() >= 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_of(pairs: &[(&str, &str)]) -> String {
+        let mut trie = Trie::new();
+        for (key, value) in pairs {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        hex::encode(trie.root_hash())
+    }
+
+    #[test]
+    fn test_empty_root() {
+        assert_eq!(Trie::new().root_hash(), EMPTY_ROOT);
+        assert_eq!(keccak256(&[]), EMPTY_CODE_HASH);
+        assert_eq!(keccak256(&rlp::encode_bytes(&[])), EMPTY_ROOT);
+    }
+
+    /// Vectors from ethereum/tests TrieTests/trietest.json
+    #[test]
+    fn test_reference_roots() {
+        assert_eq!(
+            root_of(&[("A", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")]),
+            "d23786fb4a010da3ce639d66d5e904a11dbc02746d1ce25029e53290cabf28ab"
+        );
+        assert_eq!(
+            root_of(&[
+                ("doe", "reindeer"),
+                ("dog", "puppy"),
+                ("dogglesworth", "cat")
+            ]),
+            "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        );
+        // Insertion order must not matter
+        assert_eq!(
+            root_of(&[
+                ("dogglesworth", "cat"),
+                ("dog", "puppy"),
+                ("doe", "reindeer")
+            ]),
+            "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        );
+    }
+
+    /// Keys sharing 0, 1 and 2 nibbles give a branch per shared nibble
+    #[test]
+    fn test_path_through_shared_prefixes() {
+        let mut keys = [[0u8; 32]; 3];
+        keys[0][0] = 0x10;
+        keys[1][0] = 0x20;
+        keys[2][0] = 0x21;
+
+        let mut trie = Trie::new();
+        for key in &keys {
+            trie.insert(key, vec![1]);
+        }
+
+        let report = trie.report(&keys[2]);
+        assert_eq!(
+            report.node_types,
+            vec![NodeKind::Branch, NodeKind::Branch, NodeKind::Leaf]
+        );
+        assert_eq!(trie.report(&keys[0]).depth, 2);
+        assert!(trie.path(&[0xff; 32]).is_empty());
+    }
+
+    #[test]
+    fn test_proof_report() {
+        let mut trie = Trie::new();
+        for first in [0x10, 0x20, 0x21] {
+            trie.insert(&[first; 32], vec![1]);
+        }
+
+        // Each branch on the way carries the hash of its other (leaf) child: the root holds
+        // 14 empty children, two 33-byte hash references and an empty value
+        let proof = trie.report(&[0x21; 32]).proof.unwrap();
+        assert_eq!(proof.sibling_hashes, 2);
+        assert_eq!(proof.node_sizes.len(), 3);
+        assert_eq!(proof.node_sizes[0], 2 + 14 + 2 * 33 + 1);
+        assert_eq!(proof.total_bytes, proof.node_sizes.iter().sum::<usize>());
+    }
+
+    #[test]
+    fn test_shape_of_shared_prefixes() {
+        let mut trie = Trie::new();
+        for first in [0x10, 0x20, 0x21] {
+            trie.insert(&[first, 0x00], vec![1]);
+        }
+
+        let shape = trie.shape();
+        assert_eq!(shape.leaves, 3);
+        assert_eq!(shape.branch_nodes, 2);
+        assert_eq!(shape.max_depth, 3);
+        assert_eq!(shape.leaf_depths, vec![0, 0, 1, 2]);
+    }
+
+    /// One key under every nibble saturates the root branch
+    #[test]
+    fn test_shape_counts_full_branches() {
+        let mut trie = Trie::new();
+        for nibble in 0..16u8 {
+            trie.insert(&[nibble << 4, 0x01], vec![1]);
+        }
+        trie.insert(&[0x00, 0x02], vec![1]);
+
+        let shape = trie.shape();
+        assert_eq!(shape.full_branch_nodes, 1);
+        assert_eq!(shape.branch_nodes, 2);
+        assert_eq!(shape.extension_nodes, 1);
+        assert_eq!(shape.max_depth, 4);
+    }
+}
@@ -0,0 +1,52 @@
+//! # RLP Module
+//!
+//! Recursive Length Prefix encoding, as used by the Merkle Patricia Trie nodes and accounts.
+//! Only encoding is needed: lists take their items already encoded.
+
+/// Encode a byte string
+pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return vec![bytes[0]];
+    }
+
+    let mut out = encode_length(bytes.len(), 0x80);
+    out.extend_from_slice(bytes);
+    out
+}
+
+/// Encode a list whose items are already RLP encoded
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_len = items.iter().map(Vec::len).sum();
+    let mut out = encode_length(payload_len, 0xc0);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+/// Encode an unsigned integer as a big-endian byte string without leading zeros
+pub fn encode_u64(value: u64) -> Vec<u8> {
+    encode_bytes(trim_leading_zeros(&value.to_be_bytes()))
+}
+
+/// Encode an unsigned integer as a big-endian byte string without leading zeros
+pub fn encode_u128(value: u128) -> Vec<u8> {
+    encode_bytes(trim_leading_zeros(&value.to_be_bytes()))
+}
+
+/// Strip leading zero bytes (an all-zero value becomes empty)
+pub fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let first = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[first..]
+}
+
+fn encode_length(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+
+    let len_bytes = trim_leading_zeros(&len.to_be_bytes()).to_vec();
+    let mut out = vec![offset + 55 + len_bytes.len() as u8];
+    out.extend_from_slice(&len_bytes);
+    out
+}
@@ -0,0 +1,235 @@
+//! # Search Module
+//!
+//! Every miner looks for the same thing: a candidate (a mapping key, an address, a salt
+//! counter, a private key) whose derived 32-byte key (a hashed storage slot or account key)
+//! shares a prefix with a target. A search is put together from three parts: a candidate
+//! generator, a key derivation and a predicate. It runs on the mining engine without any
+//! locking per attempt, so supporting a new kind of key only takes new closures, not another
+//! worker.
+//!
+//! ## Key Functions
+//! - `submit`: Queues a search built from a generator, a key derivation and a predicate
+//! - `Prefix`: Predicate of the prefix searches (at least, or exactly, N shared nibbles or bits)
+//! - `random_address` / `random_eoa`: Candidate generators for plain addresses and EOAs
+//! - `has_nibble_prefix` / `count_shared_nibbles` / `prefix_matches`: Nibble comparisons of keys
+//! - `has_bit_prefix` / `count_shared_bits` / `bit_prefix_matches`: Bit comparisons of keys, for
+//!   the binary tree
+
+use crate::engine::{JobHandle, MiningEngine};
+use crate::keys;
+use secp256k1::{Secp256k1, SecretKey, SignOnly};
+
+/// Attempts per engine batch for candidates that only need hashing
+pub const HASH_BATCH_SIZE: u64 = 1000;
+/// Attempts per engine batch for private keys, whose public key derivation is far slower
+pub const KEY_BATCH_SIZE: u64 = 100;
+
+/// Granularity of a prefix: nibbles for the hexary Merkle Patricia Trie, bits for the binary
+/// tree of EIP-7864
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefixUnit {
+    Nibble,
+    Bit,
+}
+
+/// Target of a prefix search: keys sharing `length` nibbles (or bits) with `target`, and with
+/// `exact` differing in the nibble (or bit) after them
+#[derive(Clone, Copy, Debug)]
+pub struct Prefix {
+    pub target: [u8; 32],
+    pub length: usize,
+    pub unit: PrefixUnit,
+    pub exact: bool,
+}
+
+impl Prefix {
+    pub fn new(target: [u8; 32], nibbles: usize, exact: bool) -> Self {
+        Prefix {
+            target,
+            length: nibbles,
+            unit: PrefixUnit::Nibble,
+            exact,
+        }
+    }
+
+    pub fn bits(target: [u8; 32], bits: usize, exact: bool) -> Self {
+        Prefix {
+            target,
+            length: bits,
+            unit: PrefixUnit::Bit,
+            exact,
+        }
+    }
+
+    pub fn matches(&self, key: &[u8; 32]) -> bool {
+        match self.unit {
+            PrefixUnit::Nibble => prefix_matches(key, &self.target, self.length, self.exact),
+            PrefixUnit::Bit => bit_prefix_matches(key, &self.target, self.length, self.exact),
+        }
+    }
+}
+
+/// Queue a search for the first candidate whose derived key satisfies `predicate`
+///
+/// `generate` gets a fast non-cryptographic RNG and the attempt number; batches hand out
+/// disjoint attempt ranges, so counter-based generators never test a candidate twice.
+pub fn submit<C, G, D, P>(
+    engine: &MiningEngine,
+    priority: i64,
+    batch_size: u64,
+    generate: G,
+    derive: D,
+    predicate: P,
+) -> JobHandle<C>
+where
+    C: Send + 'static,
+    G: Fn(&mut fastrand::Rng, u64) -> C + Send + Sync + 'static,
+    D: Fn(&C) -> [u8; 32] + Send + Sync + 'static,
+    P: Fn(&[u8; 32]) -> bool + Send + Sync + 'static,
+{
+    engine.submit(
+        priority,
+        batch_size,
+        Box::new(move |batch| {
+            let mut rng = fastrand::Rng::new();
+            batch
+                .map(|attempt| generate(&mut rng, attempt))
+                .find(|candidate| predicate(&derive(candidate)))
+        }),
+    )
+}
+
+/// Generator of uniformly random 20-byte keys (mapping keys or plain addresses)
+pub fn random_address(rng: &mut fastrand::Rng, _attempt: u64) -> [u8; 20] {
+    let mut address = [0u8; 20];
+    rng.fill(&mut address);
+    address
+}
+
+/// Generator of EOAs: a private key from the OS-seeded cryptographic RNG, together with its
+/// address
+pub fn random_eoa(
+    secp: Secp256k1<SignOnly>,
+) -> impl Fn(&mut fastrand::Rng, u64) -> ([u8; 20], SecretKey) {
+    move |_, _| {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        (keys::address_of(&secp, &key), key)
+    }
+}
+
+/// Check that two keys share `nibbles` nibbles, and with `exact` that the next nibble
+/// differs, so the keys split at a branch node exactly at that depth
+pub fn prefix_matches(a: &[u8; 32], b: &[u8; 32], nibbles: usize, exact: bool) -> bool {
+    if exact {
+        count_shared_nibbles(a, b) == nibbles
+    } else {
+        has_nibble_prefix(a, b, nibbles)
+    }
+}
+
+/// Check if two keys share a prefix of the specified number of nibbles
+pub fn has_nibble_prefix(a: &[u8; 32], b: &[u8; 32], nibbles: usize) -> bool {
+    let full_bytes = nibbles / 2;
+    if a[..full_bytes] != b[..full_bytes] {
+        return false;
+    }
+
+    // Check the half byte (single nibble) if needed
+    nibbles.is_multiple_of(2) || (a[full_bytes] ^ b[full_bytes]) & 0xF0 == 0
+}
+
+/// Count how many nibbles two keys share
+pub fn count_shared_nibbles(a: &[u8; 32], b: &[u8; 32]) -> usize {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(byte) => byte * 2 + usize::from((a[byte] ^ b[byte]) & 0xF0 == 0),
+        None => 64,
+    }
+}
+
+/// Check that two keys share `bits` bits, and with `exact` that the next bit differs, so the
+/// keys split at an internal node of the binary tree exactly at that depth
+pub fn bit_prefix_matches(a: &[u8; 32], b: &[u8; 32], bits: usize, exact: bool) -> bool {
+    if exact {
+        count_shared_bits(a, b) == bits
+    } else {
+        has_bit_prefix(a, b, bits)
+    }
+}
+
+/// Check if two keys share a prefix of the specified number of bits
+pub fn has_bit_prefix(a: &[u8; 32], b: &[u8; 32], bits: usize) -> bool {
+    let full_bytes = bits / 8;
+    if a[..full_bytes] != b[..full_bytes] {
+        return false;
+    }
+
+    // Compare the leading bits of the partial byte, if any
+    let rest = bits % 8;
+    rest == 0 || (a[full_bytes] ^ b[full_bytes]) >> (8 - rest) == 0
+}
+
+/// Count how many leading bits two keys share
+pub fn count_shared_bits(a: &[u8; 32], b: &[u8; 32]) -> usize {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(byte) => byte * 8 + (a[byte] ^ b[byte]).leading_zeros() as usize,
+        None => 256,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nibble_comparisons() {
+        let a = [0x12; 32];
+        let mut b = a;
+        b[2] = 0x1f;
+
+        assert_eq!(count_shared_nibbles(&a, &b), 5);
+        assert_eq!(count_shared_nibbles(&a, &a), 64);
+        assert!(has_nibble_prefix(&a, &b, 5));
+        assert!(!has_nibble_prefix(&a, &b, 6));
+        assert!(has_nibble_prefix(&a, &a, 64));
+
+        assert!(prefix_matches(&a, &b, 4, false));
+        assert!(!prefix_matches(&a, &b, 4, true));
+        assert!(prefix_matches(&a, &b, 5, true));
+    }
+
+    #[test]
+    fn test_bit_comparisons() {
+        let a = [0x12; 32];
+        let mut b = a;
+        // 0x12 = 0001_0010 and 0x16 = 0001_0110 first differ in bit 5 of the byte
+        b[2] = 0x16;
+
+        assert_eq!(count_shared_bits(&a, &b), 21);
+        assert_eq!(count_shared_bits(&a, &a), 256);
+        assert!(has_bit_prefix(&a, &b, 21));
+        assert!(!has_bit_prefix(&a, &b, 22));
+        assert!(has_bit_prefix(&a, &a, 256));
+
+        assert!(Prefix::bits(b, 20, false).matches(&a));
+        assert!(!Prefix::bits(b, 20, true).matches(&a));
+        assert!(Prefix::bits(b, 21, true).matches(&a));
+    }
+
+    #[test]
+    fn test_counter_search() {
+        let engine = MiningEngine::new(2);
+        let target = [0x7c; 32];
+        let search = submit(
+            &engine,
+            0,
+            HASH_BATCH_SIZE,
+            |_, attempt| attempt,
+            |counter| crate::account_miner::keccak256(&counter.to_be_bytes()),
+            move |key| Prefix::new(target, 2, true).matches(key),
+        );
+
+        let counter = search.wait().unwrap();
+        let key = crate::account_miner::keccak256(&counter.to_be_bytes());
+        assert_eq!(count_shared_nibbles(&key, &target), 2);
+    }
+}
@@ -0,0 +1,201 @@
+//! # Snapshot Module
+//!
+//! Loads trie keys that already exist in a real state (hashed account keys, or the hashed
+//! storage slots of a target contract) and finds the deepest existing path among them. Mining can
+//! then start from that path, so every mined level adds depth below real state instead of
+//! starting a fresh branch next to it.
+//!
+//! ## Key Functions
+//! - `load_keys`: Reads keys from a text file (one hex key per line) or a JSON array
+//! - `deepest_prefix`: Picks the pair of existing keys sharing the longest nibble prefix
+
+use crate::account_miner::keccak256;
+use crate::search::count_shared_nibbles;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Which trie the snapshot keys belong to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotKind {
+    /// Storage slots of the target contract (32-byte hex), hashed into their trie keys like
+    /// the mined slots
+    Storage,
+    /// Hashed account keys (32-byte hex), or plain 20-byte addresses that get hashed
+    Accounts,
+}
+
+/// The existing path mining continues from
+#[derive(Clone, Debug)]
+pub struct SnapshotAnchor {
+    /// Existing (hashed) trie key at the end of the deepest path
+    pub key: [u8; 32],
+    /// Nibbles that key shares with its closest existing neighbour
+    pub shared_nibbles: usize,
+    /// Number of distinct keys in the snapshot
+    pub num_keys: usize,
+}
+
+/// Serializable description of the anchor, written to the result JSON
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub key: String,
+    pub shared_nibbles: usize,
+    pub snapshot_keys: usize,
+}
+
+impl SnapshotAnchor {
+    /// Nibbles the first mined key must share with the anchor to sit below the existing branch
+    pub fn required_nibbles(&self) -> usize {
+        self.shared_nibbles + 1
+    }
+
+    pub fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            key: format!("0x{}", hex::encode(self.key)),
+            shared_nibbles: self.shared_nibbles,
+            snapshot_keys: self.num_keys,
+        }
+    }
+}
+
+impl SnapshotInfo {
+    /// Rebuild the anchor from a deserialized result
+    pub fn to_anchor(&self) -> Result<SnapshotAnchor, String> {
+        Ok(SnapshotAnchor {
+            key: crate::parse_hex_array(&self.key)?,
+            shared_nibbles: self.shared_nibbles,
+            num_keys: self.snapshot_keys,
+        })
+    }
+}
+
+/// Load trie keys from a file with one hex key per line (blank lines and `#` comments are
+/// skipped) or from a JSON array of hex strings
+pub fn load_keys(path: &str, kind: SnapshotKind) -> Result<Vec<[u8; 32]>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+
+    let entries: Vec<String> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON key list: {e}"))?
+    } else {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let digits = entry.strip_prefix("0x").unwrap_or(entry).len();
+            match (kind, digits) {
+                (SnapshotKind::Accounts, 40) => {
+                    let address: [u8; 20] = crate::parse_hex_array(entry)?;
+                    Ok(keccak256(&address))
+                }
+                (SnapshotKind::Storage, _) => {
+                    let slot: [u8; 32] =
+                        crate::parse_hex_array(entry).map_err(|e| format!("Key {entry}: {e}"))?;
+                    Ok(keccak256(&slot))
+                }
+                _ => crate::parse_hex_array(entry).map_err(|e| format!("Key {entry}: {e}")),
+            }
+        })
+        .collect()
+}
+
+/// Find the existing key at the end of the deepest path
+///
+/// After sorting, the key sharing the longest prefix with any other key is always next to it,
+/// so only neighbours need to be compared.
+pub fn deepest_prefix(keys: &[[u8; 32]]) -> Option<SnapshotAnchor> {
+    let mut sorted = keys.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    if sorted.len() < 2 {
+        // A single key is a leaf right below the root
+        return sorted.first().map(|key| SnapshotAnchor {
+            key: *key,
+            shared_nibbles: 0,
+            num_keys: sorted.len(),
+        });
+    }
+
+    sorted
+        .windows(2)
+        .map(|pair| (pair[1], count_shared_nibbles(&pair[0], &pair[1])))
+        .max_by_key(|(_, shared)| *shared)
+        .map(|(key, shared_nibbles)| SnapshotAnchor {
+            key,
+            shared_nibbles,
+            num_keys: sorted.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_keys(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("snapshot_{name}_{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_keys_formats() {
+        let key = format!("0x{}", "11".repeat(32));
+        let address = "22".repeat(20);
+        let lines = write_keys("lines", &format!("# existing keys\n{key}\n\n  {address}\n"));
+        let json = write_keys("json", &format!("[\"{key}\", \"{address}\"]"));
+
+        // Account keys are already hashed, addresses still need it
+        let expected = vec![[0x11; 32], keccak256(&[0x22; 20])];
+        assert_eq!(load_keys(&lines, SnapshotKind::Accounts).unwrap(), expected);
+        assert_eq!(load_keys(&json, SnapshotKind::Accounts).unwrap(), expected);
+
+        // Storage slots are always hashed, and an address is no slot
+        let storage = write_keys("storage", &format!("{key}\n{}\n", "33".repeat(32)));
+        assert_eq!(
+            load_keys(&storage, SnapshotKind::Storage).unwrap(),
+            vec![keccak256(&[0x11; 32]), keccak256(&[0x33; 32])]
+        );
+        assert!(load_keys(&lines, SnapshotKind::Storage).is_err());
+
+        for path in [lines, json, storage] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_deepest_prefix() {
+        assert!(deepest_prefix(&[]).is_none());
+
+        let single = deepest_prefix(&[[0x12; 32]]).unwrap();
+        assert_eq!((single.key, single.shared_nibbles), ([0x12; 32], 0));
+        assert_eq!(single.required_nibbles(), 1);
+
+        // 0xab.. and 0xac.. share one nibble, 0x5612.. and 0x5613.. share three
+        let with_second_byte = |byte| {
+            let mut key = [0x56; 32];
+            key[1] = byte;
+            key
+        };
+        let near = with_second_byte(0x13);
+        let mut keys = vec![[0xab; 32], [0xac; 32], with_second_byte(0x12), near];
+        let anchor = deepest_prefix(&keys).unwrap();
+        assert_eq!((anchor.key, anchor.shared_nibbles), (near, 3));
+        assert_eq!(anchor.required_nibbles(), 4);
+        assert_eq!(anchor.num_keys, 4);
+
+        // A duplicated key is one existing leaf, not a path of 64 shared nibbles
+        keys.push([0xab; 32]);
+        let anchor = deepest_prefix(&keys).unwrap();
+        assert_eq!((anchor.shared_nibbles, anchor.num_keys), (3, 4));
+    }
+}
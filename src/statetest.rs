@@ -0,0 +1,336 @@
+//! # State Test Module
+//!
+//! Writes mined branches as `GeneralStateTest` fixtures in the execution-spec-tests format,
+//! so any client's state test runner can replay the worst case without a network. A fixture
+//! holds the pre-state (the contract with the mined storage branch, or the mined CREATE2
+//! accounts), one attack transaction, and the expected post-state with its state root.
+//!
+//! The transaction pays no fees (gas price and base fee are zero), so the post-state does
+//! not depend on the exact gas the attack uses: only the sender's nonce and the attacked
+//! slot change. Storage roots are built from keccak256 of each slot, as clients store them.
+//!
+//! ## Key Functions
+//! - `storage_test`: Fixture whose transaction SSTOREs into the deepest mined slot
+//! - `create2_test`: Fixture whose transaction reads every mined account from the trie
+//! - `write_fixture`: Writes a named fixture file
+
+use crate::account_miner::{Create2MiningResult, keccak256};
+use crate::bytecode::{self, RuntimeKind, selector};
+use crate::genesis::{self, Alloc, AllocAccount, Predeploy};
+use crate::layout::SlotLayout;
+use crate::storage_miner::StorageSlot;
+use crate::{keys, parse_hex_array, rlp};
+use secp256k1::Secp256k1;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Sender key used throughout the execution-spec-tests fixtures
+const SENDER_KEY: &str = "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+const SENDER_BALANCE: &str = "0x0ba1a9ce0ba1a9ce";
+/// Default coinbase of the execution-spec-tests environments
+const COINBASE: &str = "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba";
+/// Account the touch contract of CREATE2 fixtures is placed at
+const TOUCH_CONTRACT: [u8; 20] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x70, 0x0c,
+];
+/// Upper bound of the gas one touched account costs (a cold BALANCE plus the PUSH20 and POP)
+const TOUCH_GAS: u64 = 2_600 + 3 + 2;
+const TRANSACTION_GAS: u64 = 21_000;
+
+/// A `GeneralStateTest` fixture
+#[derive(Serialize)]
+pub struct StateTest {
+    pub env: Env,
+    pub pre: BTreeMap<String, FixtureAccount>,
+    pub transaction: Transaction,
+    pub post: BTreeMap<String, Vec<PostState>>,
+}
+
+/// Block environment the transaction runs in
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    pub current_coinbase: String,
+    pub current_gas_limit: String,
+    pub current_number: String,
+    pub current_timestamp: String,
+    pub current_difficulty: String,
+    pub current_random: String,
+    pub current_base_fee: String,
+    pub current_excess_blob_gas: String,
+}
+
+/// An account of the pre- or post-state, with every field present as runners expect
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FixtureAccount {
+    pub nonce: String,
+    pub balance: String,
+    pub code: String,
+    pub storage: BTreeMap<String, String>,
+}
+
+/// The transaction of a fixture; gas limit, value and data are lists indexed by `indexes`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub nonce: String,
+    pub gas_price: String,
+    pub gas_limit: Vec<String>,
+    pub to: String,
+    pub value: Vec<String>,
+    pub data: Vec<String>,
+    pub sender: String,
+    pub secret_key: String,
+}
+
+/// Expected outcome of the transaction under one fork
+#[derive(Serialize)]
+pub struct PostState {
+    /// State root after the transaction
+    pub hash: String,
+    /// keccak256 of the RLP list of logs
+    pub logs: String,
+    pub indexes: Indexes,
+    pub state: BTreeMap<String, FixtureAccount>,
+}
+
+#[derive(Serialize)]
+pub struct Indexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+/// Fixture for a contract at `address` holding the mined branch: the transaction calls
+/// `attack(value)`, which SSTOREs `value` into the deepest slot (0 deletes it)
+pub fn storage_test(
+    branch: &[StorageSlot],
+    kind: RuntimeKind,
+    layout: &SlotLayout,
+    address: &[u8; 20],
+    value: &[u8; 32],
+    gas_limit: u64,
+    fork: &str,
+) -> Result<StateTest, String> {
+    let Some(deepest) = branch.last() else {
+        return Err("The storage branch is empty".to_string());
+    };
+    if kind == RuntimeKind::None {
+        return Err("The attack transaction needs a runtime with attack(uint256)".to_string());
+    }
+
+    let sender = sender_address()?;
+    let pre = genesis::contract_alloc(
+        address,
+        &Predeploy::from_branch(branch, kind, layout, &sender)?,
+    );
+    let mut data = selector("attack(uint256)").to_vec();
+    data.extend_from_slice(value);
+
+    let mut post = pre.clone();
+    let storage = &mut post
+        .get_mut(&format!("0x{}", hex::encode(address)))
+        .expect("contract is in the alloc")
+        .storage;
+    let key = format!("0x{}", hex::encode(deepest.storage_key));
+    if *value == [0u8; 32] {
+        storage.remove(&key);
+    } else {
+        storage.insert(key, format!("0x{}", hex::encode(value)));
+    }
+
+    state_test(pre, post, address, data, gas_limit, fork)
+}
+
+/// Fixture for the accounts of a CREATE2 result (contracts pre-deployed when `predeploy` is
+/// given): the transaction calls a contract that reads the balance of every contract and
+/// auxiliary account, walking each one's path in the state trie
+pub fn create2_test(
+    result: &Create2MiningResult,
+    balance: &[u8; 32],
+    predeploy: Option<&Predeploy>,
+    gas_limit: u64,
+    fork: &str,
+) -> Result<StateTest, String> {
+    let mut touched = Vec::new();
+    for contract in &result.contracts {
+        touched.push(parse_hex_array::<20>(&contract.contract_address)?);
+        for auxiliary in &contract.auxiliary_accounts {
+            touched.push(parse_hex_array::<20>(auxiliary)?);
+        }
+    }
+    let needed = TRANSACTION_GAS + TOUCH_GAS * touched.len() as u64;
+    if needed > gas_limit {
+        return Err(format!(
+            "Touching {} accounts needs up to {needed} gas, more than the gas limit {gas_limit}",
+            touched.len()
+        ));
+    }
+
+    let mut pre = genesis::create2_alloc(result, balance, predeploy)?;
+    let toucher = Predeploy {
+        code: bytecode::touch_code(&touched),
+        storage: Vec::new(),
+    };
+    pre.extend(genesis::contract_alloc(&TOUCH_CONTRACT, &toucher));
+
+    // Reading balances changes nothing but the sender's nonce
+    let post = pre.clone();
+    state_test(pre, post, &TOUCH_CONTRACT, Vec::new(), gas_limit, fork)
+}
+
+/// Assemble a fixture around one fee-less transaction from the fixture sender
+fn state_test(
+    mut pre: Alloc,
+    mut post: Alloc,
+    to: &[u8; 20],
+    data: Vec<u8>,
+    gas_limit: u64,
+    fork: &str,
+) -> Result<StateTest, String> {
+    let sender = format!("0x{}", hex::encode(sender_address()?));
+    let sender_account = |nonce: &str| AllocAccount {
+        balance: SENDER_BALANCE.to_string(),
+        nonce: Some(nonce.to_string()),
+        code: None,
+        storage: BTreeMap::new(),
+    };
+    if pre.insert(sender.clone(), sender_account("0x0")).is_some() {
+        return Err(format!(
+            "{sender} is the fixture sender and cannot be mined"
+        ));
+    }
+    post.insert(sender.clone(), sender_account("0x1"));
+
+    let post_state = PostState {
+        hash: format!("0x{}", genesis::state_dump(&post)?.root),
+        logs: format!("0x{}", hex::encode(keccak256(&rlp::encode_list(&[])))),
+        indexes: Indexes {
+            data: 0,
+            gas: 0,
+            value: 0,
+        },
+        state: fixture_accounts(&post),
+    };
+
+    Ok(StateTest {
+        env: Env {
+            current_coinbase: COINBASE.to_string(),
+            current_gas_limit: format!("0x{gas_limit:x}"),
+            current_number: "0x1".to_string(),
+            current_timestamp: "0x3e8".to_string(),
+            current_difficulty: "0x0".to_string(),
+            current_random: format!("0x{}", "00".repeat(32)),
+            current_base_fee: "0x0".to_string(),
+            current_excess_blob_gas: "0x0".to_string(),
+        },
+        pre: fixture_accounts(&pre),
+        transaction: Transaction {
+            nonce: "0x0".to_string(),
+            gas_price: "0x0".to_string(),
+            gas_limit: vec![format!("0x{gas_limit:x}")],
+            to: format!("0x{}", hex::encode(to)),
+            value: vec!["0x0".to_string()],
+            data: vec![format!("0x{}", hex::encode(data))],
+            sender,
+            secret_key: SENDER_KEY.to_string(),
+        },
+        post: BTreeMap::from([(fork.to_string(), vec![post_state])]),
+    })
+}
+
+/// Write `test` to `path` as a fixture file holding the single test `name`
+pub fn write_fixture(path: &str, name: &str, test: &StateTest) -> Result<(), String> {
+    let fixture = BTreeMap::from([(name, test)]);
+    let json =
+        serde_json::to_string_pretty(&fixture).map_err(|e| format!("Failed to serialize: {e}"))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+fn sender_address() -> Result<[u8; 20], String> {
+    let key = keys::parse_secret(SENDER_KEY)?;
+    Ok(keys::address_of(&Secp256k1::signing_only(), &key))
+}
+
+fn fixture_accounts(alloc: &Alloc) -> BTreeMap<String, FixtureAccount> {
+    alloc
+        .iter()
+        .map(|(address, account)| {
+            (
+                address.clone(),
+                FixtureAccount {
+                    nonce: account.nonce.clone().unwrap_or_else(|| "0x0".to_string()),
+                    balance: account.balance.clone(),
+                    code: account.code.clone().unwrap_or_else(|| "0x".to_string()),
+                    storage: account.storage.clone(),
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_fixture_attacks_deepest_slot() {
+        let slot = |key: u8| StorageSlot {
+            address: [key; 20],
+            storage_key: [key; 32],
+            depth: 1,
+            time_taken: 0.0,
+        };
+        let branch = [slot(0x33), slot(0x34)];
+        let address = [0xaa; 20];
+        let mut value = [0u8; 32];
+        value[31] = 2;
+
+        let test = storage_test(
+            &branch,
+            RuntimeKind::Attack,
+            &SlotLayout::default(),
+            &address,
+            &value,
+            1_000_000,
+            "Cancun",
+        )
+        .unwrap();
+        let sender = format!("0x{}", hex::encode(sender_address().unwrap()));
+        // The well-known fixture sender
+        assert_eq!(sender, "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b");
+        assert_eq!(test.pre[&sender].nonce, "0x0");
+
+        let post = &test.post["Cancun"][0];
+        assert_eq!(post.state[&sender].nonce, "0x1");
+        let contract = format!("0x{}", hex::encode(address));
+        let deepest = format!("0x{}", "34".repeat(32));
+        assert_eq!(
+            test.pre[&contract].storage[&deepest],
+            format!("0x{}01", "00".repeat(31))
+        );
+        assert_eq!(
+            post.state[&contract].storage[&deepest],
+            format!("0x{}02", "00".repeat(31))
+        );
+        assert_ne!(
+            post.hash,
+            format!("0x{}", hex::encode(crate::mpt::EMPTY_ROOT))
+        );
+
+        // Deleting the slot leaves only the other one
+        let test = storage_test(
+            &branch,
+            RuntimeKind::Attack,
+            &SlotLayout::default(),
+            &address,
+            &[0u8; 32],
+            1_000_000,
+            "Cancun",
+        )
+        .unwrap();
+        assert_eq!(test.post["Cancun"][0].state[&contract].storage.len(), 1);
+    }
+}
@@ -1,20 +1,29 @@
 //! # Storage Miner Module
 //!
 //! This module provides functionality for mining Ethereum storage slots that create worst-case
-//! scenarios in ERC20 contract storage tries. It finds addresses whose storage keys share
-//! increasingly long prefixes, forcing deep branches in the Modified Patricia Trie structure.
+//! scenarios in ERC20 contract storage tries. Clients key a storage trie by `keccak256(slot)`,
+//! so it finds addresses whose hashed storage slots share increasingly long prefixes, forcing
+//! deep branches in the Modified Patricia Trie structure.
 //!
 //! ## Key Functions
 //! - `mine_deep_branch`: Mines a sequence of addresses creating a deep storage trie branch
 //! - `calculate_storage_slot`: Computes the storage slot for an address in an ERC20 balance mapping
 //! - `generate_contract`: Creates a Solidity contract with the mined storage slots
-
+//! - `write_results`: Saves the mined branch as a structured JSON file
+//! - `load_checkpoint`: Validates a checkpoint or earlier result to resume mining from
+
+use crate::account_miner::keccak256;
+use crate::checkpoint;
+use crate::engine::MiningEngine;
+use crate::layout::{LayoutInfo, SlotLayout};
+use crate::mpt::{Trie, TrieReport};
+use crate::rlp;
+use crate::search::{self, Prefix, count_shared_nibbles, has_nibble_prefix, prefix_matches};
+use crate::snapshot::{SnapshotAnchor, SnapshotInfo};
 use askama::Template;
 use log::{debug, info};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::Instant;
 use tiny_keccak::{Hasher, Keccak};
 
@@ -25,7 +34,21 @@ use crate::cuda_miner;
 #[derive(Template)]
 #[template(path = "WorstCaseERC20.sol.j2")]
 pub struct ContractTemplate {
-    addresses: Vec<String>,
+    use_balance_of: bool,
+    accounts: Vec<String>,
+    storage_keys: Vec<String>,
+    deepest_key: String,
+    /// Layout the storage keys were derived with, for the generated comments
+    layout: String,
+}
+
+/// How the generated contract writes the mined storage slots
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContractMode {
+    /// `balanceOf[addr] = 1`, letting Solidity derive the mapping slot
+    BalanceOf,
+    /// `sstore(storage_key, 1)` with the precomputed mapping slot
+    Sstore,
 }
 
 /// Standard ERC20 balance mapping storage slot
@@ -40,6 +63,252 @@ pub struct StorageSlot {
     pub time_taken: f64, // Time taken to mine this level in seconds
 }
 
+impl StorageSlot {
+    /// Path of the slot in the storage trie, which clients key by `keccak256(slot)`
+    pub fn trie_key(&self) -> [u8; 32] {
+        keccak256(&self.storage_key)
+    }
+}
+
+/// Result structure for storage mining
+#[derive(Serialize, Deserialize)]
+pub struct StorageMiningResult {
+    pub depth: usize,
+    pub total_time: f64,
+    pub accounts: Vec<MinedStorageSlot>,
+    /// Where the mined keys' values are stored
+    #[serde(default)]
+    pub layout: LayoutInfo,
+    /// Path to the deepest key in a trie built from the mined keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trie: Option<TrieReport>,
+    /// Existing state path the branch was mined below, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotInfo>,
+    /// Whether every level shares exactly its required nibbles with the previous one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+}
+
+/// Serializable form of a single mined `StorageSlot`
+#[derive(Serialize, Deserialize)]
+pub struct MinedStorageSlot {
+    pub address: String,
+    pub storage_slot: String,
+    pub depth: usize,
+    pub time_taken: f64,
+    /// Nibbles the hashed storage key shares with the previous level (the snapshot anchor for
+    /// the first level, or 0 without one)
+    pub shared_nibbles: usize,
+}
+
+impl StorageMiningResult {
+    /// Build the serializable result from a mined branch
+    pub fn from_branch(
+        branch: &[StorageSlot],
+        total_time: f64,
+        layout: &SlotLayout,
+        anchor: Option<&SnapshotAnchor>,
+        exact: bool,
+    ) -> Self {
+        StorageMiningResult {
+            depth: branch.len(),
+            total_time,
+            accounts: mined_slots(branch, anchor),
+            layout: layout.info(),
+            trie: verify_trie(branch, anchor),
+            snapshot: anchor.map(SnapshotAnchor::info),
+            exact,
+        }
+    }
+
+    /// Rebuild the mined branch from a deserialized result
+    pub fn to_branch(&self) -> Result<Vec<StorageSlot>, String> {
+        self.accounts
+            .iter()
+            .map(MinedStorageSlot::to_slot)
+            .collect()
+    }
+}
+
+impl MinedStorageSlot {
+    /// Rebuild the `StorageSlot` from its serialized form
+    pub fn to_slot(&self) -> Result<StorageSlot, String> {
+        Ok(StorageSlot {
+            address: crate::parse_hex_array(&self.address)?,
+            storage_key: crate::parse_hex_array(&self.storage_slot)?,
+            depth: self.depth,
+            time_taken: self.time_taken,
+        })
+    }
+}
+
+/// Serialize a mined branch, counting the nibbles each level shares with the previous one
+pub fn mined_slots(
+    branch: &[StorageSlot],
+    anchor: Option<&SnapshotAnchor>,
+) -> Vec<MinedStorageSlot> {
+    branch
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| MinedStorageSlot {
+            address: format!("0x{}", hex::encode(slot.address)),
+            storage_slot: format!("0x{}", hex::encode(slot.storage_key)),
+            depth: slot.depth,
+            time_taken: slot.time_taken,
+            shared_nibbles: match (i, anchor) {
+                (0, None) => 0,
+                (0, Some(anchor)) => count_shared_nibbles(&anchor.key, &slot.trie_key()),
+                _ => count_shared_nibbles(&branch[i - 1].trie_key(), &slot.trie_key()),
+            },
+        })
+        .collect()
+}
+
+/// Insert the hashed mined storage keys (and the snapshot anchor key) into a trie and report
+/// the path to the deepest one
+pub fn verify_trie(branch: &[StorageSlot], anchor: Option<&SnapshotAnchor>) -> Option<TrieReport> {
+    let deepest = branch.last()?;
+
+    let mut trie = Trie::new();
+    if let Some(anchor) = anchor {
+        trie.insert(&anchor.key, rlp::encode_u64(1));
+    }
+    for slot in branch {
+        // The generated contracts write 1 to every mined slot
+        trie.insert(&slot.trie_key(), rlp::encode_u64(1));
+    }
+
+    Some(trie.report(&deepest.trie_key()))
+}
+
+/// Load a storage mining result from a JSON file
+pub fn load_results(path: &str) -> Result<StorageMiningResult, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid storage result JSON: {e}"))
+}
+
+/// Check that every storage key matches its address and that each level's hashed key shares
+/// at least (or, for exact results, exactly) as many nibbles with the previous one as its
+/// depth requires
+pub fn verify_results(result: &StorageMiningResult) -> Result<(), String> {
+    let branch = result.to_branch()?;
+    let layout = result.layout.to_layout()?;
+    let anchor = result
+        .snapshot
+        .as_ref()
+        .map(SnapshotInfo::to_anchor)
+        .transpose()?;
+
+    if branch.len() != result.depth {
+        return Err(format!(
+            "Result claims depth {} but contains {} levels",
+            result.depth,
+            branch.len()
+        ));
+    }
+
+    verify_levels(&branch, &layout, anchor.as_ref(), result.exact)?;
+
+    if let Some(report) = verify_trie(&branch, anchor.as_ref()) {
+        info!(
+            "Trie path to the deepest key: {} nodes ({})",
+            report.depth,
+            format_node_path(&report)
+        );
+        if let Some(proof) = &report.proof {
+            info!("Storage proof: {}", proof.describe());
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that every storage key follows from its mined key and that each level's hashed key
+/// shares at least as many nibbles with the previous one (or the anchor) as its depth requires,
+/// and with `exact` no more than that
+pub fn verify_levels(
+    branch: &[StorageSlot],
+    layout: &SlotLayout,
+    anchor: Option<&SnapshotAnchor>,
+    exact: bool,
+) -> Result<(), String> {
+    let base_nibbles = anchor.map_or(0, SnapshotAnchor::required_nibbles);
+
+    for (i, slot) in branch.iter().enumerate() {
+        if layout.storage_key(&slot.address) != slot.storage_key {
+            return Err(format!(
+                "Level {}: storage key 0x{} does not match key 0x{} in {}",
+                i + 1,
+                hex::encode(slot.storage_key),
+                hex::encode(slot.address),
+                layout.describe()
+            ));
+        }
+
+        let reference = match (i, anchor) {
+            (0, None) => continue,
+            (0, Some(anchor)) => anchor.key,
+            _ => branch[i - 1].trie_key(),
+        };
+        let trie_key = slot.trie_key();
+        if !has_nibble_prefix(&trie_key, &reference, base_nibbles + i) {
+            return Err(format!(
+                "Level {}: storage key shares fewer than {} nibbles with the previous level",
+                i + 1,
+                base_nibbles + i
+            ));
+        }
+        if exact && !prefix_matches(&trie_key, &reference, base_nibbles + i, true) {
+            return Err(format!(
+                "Level {}: storage key shares more than {} nibbles with the previous level",
+                i + 1,
+                base_nibbles + i
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Load a checkpoint (or a finished result) and check it can be extended to `target_depth`
+/// with the same slot layout and prefix mode
+///
+/// Every stored level is re-verified, so a damaged checkpoint is rejected instead of
+/// silently continuing from a wrong key.
+pub fn load_checkpoint(
+    path: &str,
+    target_depth: usize,
+    layout: &SlotLayout,
+    exact: bool,
+) -> Result<StorageMiningResult, String> {
+    let result = load_results(path)?;
+    verify_results(&result)?;
+
+    if result.exact != exact {
+        return Err(format!(
+            "Checkpoint was mined {} exact prefixes",
+            if result.exact { "with" } else { "without" }
+        ));
+    }
+    if result.depth > target_depth {
+        return Err(format!(
+            "Checkpoint already holds {} levels, more than the target depth {target_depth}",
+            result.depth
+        ));
+    }
+    let mined_layout = result.layout.to_layout()?;
+    if mined_layout != *layout {
+        return Err(format!(
+            "Checkpoint was mined for {}, not {}",
+            mined_layout.describe(),
+            layout.describe()
+        ));
+    }
+
+    Ok(result)
+}
+
 /// Calculate the storage slot for a given address in the balances mapping
 pub fn calculate_storage_slot(address: &[u8; 20], base_slot: u64) -> [u8; 32] {
     let mut hasher = Keccak::v256();
@@ -63,17 +332,49 @@ pub fn calculate_storage_slot(address: &[u8; 20], base_slot: u64) -> [u8; 32] {
 }
 
 /// Mine for a deep branch by finding addresses sequentially, one depth at a time
+///
+/// With a snapshot anchor, the first level already has to share one nibble more than the
+/// deepest existing path, and every further level adds one nibble on top of that.
+///
+/// With `exact`, each level shares exactly its required nibbles with the previous one, so
+/// no level skips past the branch node it is meant to create.
+///
+/// Mining continues after the `resumed` levels. With a `checkpoint_path`, the branch is
+/// saved there after every level.
+#[allow(clippy::too_many_arguments)]
 pub fn mine_deep_branch(
     target_depth: usize,
     num_threads: usize,
     use_cuda: bool,
+    layout: &SlotLayout,
+    anchor: Option<&SnapshotAnchor>,
+    exact: bool,
+    resumed: Vec<StorageSlot>,
+    checkpoint_path: Option<&str>,
 ) -> Vec<StorageSlot> {
-    let mut branch = Vec::new();
+    let mut branch = resumed;
+    let base_nibbles = anchor.map_or(0, SnapshotAnchor::required_nibbles);
 
     info!("Starting sequential mining for {target_depth} levels");
+    info!("Storage layout: {}", layout.describe());
+    if !branch.is_empty() {
+        info!(
+            "Continuing at level {} with {} levels already mined",
+            branch.len() + 1,
+            branch.len()
+        );
+    }
+    if let Some(anchor) = anchor {
+        info!(
+            "Extending existing path at 0x{}... ({} shared nibbles among {} snapshot keys)",
+            hex::encode(&anchor.key[..anchor.shared_nibbles.div_ceil(2).max(1)]),
+            anchor.shared_nibbles,
+            anchor.num_keys
+        );
+    }
 
     // For each depth level, find an address that creates the right prefix collision
-    for current_depth in 0..target_depth {
+    for current_depth in branch.len()..target_depth {
         let level_start = Instant::now();
 
         // Each level should share an increasing number of nibbles:
@@ -81,7 +382,8 @@ pub fn mine_deep_branch(
         // Level 2: 1 shared nibble with level 1
         // Level 3: 2 shared nibbles with levels 1 & 2
         // Level N: N-1 shared nibbles with all previous levels
-        let required_prefix_nibbles = current_depth;
+        // A snapshot anchor shifts all of these by the depth of the existing path
+        let required_prefix_nibbles = base_nibbles + current_depth;
 
         info!(
             "Mining level {}/{} (requires {} matching nibbles)",
@@ -91,22 +393,27 @@ pub fn mine_deep_branch(
         );
 
         // Mine for an address at this depth level
-        let address = if current_depth == 0 {
+        let address = if current_depth == 0 && anchor.is_none() {
             // First address can be anything - just generate a random one
             let mut addr = [0u8; 20];
             fastrand::fill(&mut addr);
             addr
         } else {
-            // Need to find an address that shares the required prefix with the PREVIOUS level
-            // (not all previous addresses, just the immediately preceding one)
-            let previous_slot: &StorageSlot = &branch[branch.len() - 1];
+            // Need to find an address whose hashed key shares the required prefix with the
+            // PREVIOUS level (not all previous addresses, just the immediately preceding one)
+            let previous_key = match branch.last() {
+                Some(previous_slot) => previous_slot.trie_key(),
+                None => anchor.expect("anchor checked above").key,
+            };
             // Only use CUDA for depth 8+ where the computational cost justifies the overhead
-            let use_cuda_for_level = use_cuda && current_depth >= 8;
+            let use_cuda_for_level = use_cuda && required_prefix_nibbles >= 8;
             match mine_address_for_prefix(
-                &previous_slot.storage_key,
+                &previous_key,
                 required_prefix_nibbles,
+                exact,
                 num_threads,
                 use_cuda_for_level,
+                layout,
             ) {
                 Some(addr) => addr,
                 None => {
@@ -119,7 +426,7 @@ pub fn mine_deep_branch(
             }
         };
 
-        let storage_key = calculate_storage_slot(&address, ERC20_BALANCES_SLOT);
+        let storage_key = layout.storage_key(&address);
 
         let level_time = level_start.elapsed();
 
@@ -131,23 +438,35 @@ pub fn mine_deep_branch(
         });
 
         info!(
-            "Level {} found in {:.2} seconds - Address: 0x{}, Storage: 0x{}...",
+            "Level {} found in {:.2} seconds - Address: 0x{}, Trie key: 0x{}...",
             current_depth + 1,
             level_time.as_secs_f64(),
             hex::encode(&address[..4]),
-            hex::encode(&storage_key[..4])
+            hex::encode(&keccak256(&storage_key)[..4])
         );
+
+        if let Some(path) = checkpoint_path {
+            let mined_time = branch.iter().map(|slot| slot.time_taken).sum();
+            let progress =
+                StorageMiningResult::from_branch(&branch, mined_time, layout, anchor, exact);
+            match checkpoint::save(path, &progress) {
+                Ok(()) => debug!("Checkpoint saved to {path}"),
+                Err(e) => log::error!("Failed to save checkpoint: {e}"),
+            }
+        }
     }
 
     branch
 }
 
-/// Mine for a single address that shares a prefix with the target storage key
-fn mine_address_for_prefix(
-    target_storage_key: &[u8; 32],
+/// Mine for a single address whose hashed storage key shares a prefix with the target trie key
+pub(crate) fn mine_address_for_prefix(
+    target_trie_key: &[u8; 32],
     required_prefix_nibbles: usize,
+    exact: bool,
     num_threads: usize,
     #[allow(unused_variables)] use_cuda: bool,
+    layout: &SlotLayout,
 ) -> Option<[u8; 20]> {
     #[cfg(feature = "cuda")]
     {
@@ -157,156 +476,74 @@ fn mine_address_for_prefix(
                 required_prefix_nibbles
             );
             // Try CUDA mining first
-            if let Some((address, _storage_key)) = cuda_miner::mine_with_cuda(
-                target_storage_key,
-                required_prefix_nibbles,
-                ERC20_BALANCES_SLOT,
-            ) {
+            if let Some((address, _storage_key)) =
+                cuda_miner::mine_with_cuda(target_trie_key, required_prefix_nibbles, exact, layout)
+            {
                 return Some(address);
             }
             info!("CUDA mining failed, falling back to CPU");
         }
     }
-    let result = Arc::new(Mutex::new(None));
-    let found = Arc::new(AtomicBool::new(false));
-
-    let handles: Vec<_> = (0..num_threads)
-        .map(|thread_id| {
-            let result_clone = Arc::clone(&result);
-            let found_clone = Arc::clone(&found);
-            let target = *target_storage_key;
-
-            thread::spawn(move || {
-                mine_worker_for_prefix(
-                    thread_id,
-                    &target,
-                    required_prefix_nibbles,
-                    result_clone,
-                    found_clone,
-                );
-            })
-        })
-        .collect();
-
-    for handle in handles {
-        handle.join().unwrap();
-    }
-
-    *result.lock().unwrap()
+    // The layout caches the slot of the mapping indexed by the mined key
+    let prefix = Prefix::new(*target_trie_key, required_prefix_nibbles, exact);
+    let layout = layout.clone();
+    let search = search::submit(
+        &MiningEngine::shared(num_threads),
+        0,
+        search::HASH_BATCH_SIZE,
+        search::random_address,
+        move |address| keccak256(&layout.storage_key(address)),
+        move |trie_key| prefix.matches(trie_key),
+    );
+    let address = search.wait()?;
+    info!(
+        "Found matching address after about {} attempts",
+        search.attempts()
+    );
+    Some(address)
 }
 
-fn mine_worker_for_prefix(
-    thread_id: usize,
-    target_prefix: &[u8; 32],
-    required_nibbles: usize,
-    result: Arc<Mutex<Option<[u8; 20]>>>,
-    found: Arc<AtomicBool>,
+pub fn print_results(
+    branch: &[StorageSlot],
+    elapsed_seconds: f64,
+    layout: &SlotLayout,
+    anchor: Option<&SnapshotAnchor>,
 ) {
-    // Use fastrand for faster non-crypto PRNG
-    let mut rng = fastrand::Rng::new();
-    let mut attempts = 0u64;
-
-    // Pre-compute the slot bytes since they don't change
-    let mut slot_bytes = [0u8; 32];
-    slot_bytes[24..32].copy_from_slice(&ERC20_BALANCES_SLOT.to_be_bytes());
-
-    // Batch size for checking - check found flag less often
-    const BATCH_SIZE: u64 = 1000;
-
-    loop {
-        // Check if another thread found a result (but only every BATCH_SIZE attempts)
-        // Using Relaxed ordering - sufficient for a stop flag, no synchronization needed
-        if attempts % BATCH_SIZE == 0 && found.load(Ordering::Relaxed) {
-            break;
-        }
-
-        attempts += 1;
-        if attempts % 1000000 == 0 {
-            debug!(
-                "Thread {}: {} million attempts",
-                thread_id,
-                attempts / 1000000
-            );
-        }
-
-        // Generate a random address using fastrand
-        let mut address = [0u8; 20];
-        rng.fill(&mut address);
-
-        // Calculate storage key inline for better performance
-        use tiny_keccak::{Hasher, Keccak};
-        let mut hasher = Keccak::v256();
-        let mut storage_key = [0u8; 32];
-
-        // Prepare padded address
-        let mut padded_address = [0u8; 32];
-        padded_address[12..32].copy_from_slice(&address);
-
-        // Hash in one go
-        hasher.update(&padded_address);
-        hasher.update(&slot_bytes);
-        hasher.finalize(&mut storage_key);
-
-        // Check if it matches the required prefix
-        if has_nibble_prefix(&storage_key, target_prefix, required_nibbles) {
-            // Use compare_exchange for atomic flag setting
-            if found
-                .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
-                .is_ok()
-            {
-                let mut result_lock = result.lock().unwrap();
-                *result_lock = Some(address);
-                info!("Thread {thread_id} found matching address after {attempts} attempts");
-            }
-            break;
-        }
-    }
-}
-
-/// Check if two storage keys share a prefix of the specified number of nibbles
-pub fn has_nibble_prefix(a: &[u8; 32], b: &[u8; 32], nibbles: usize) -> bool {
-    if nibbles == 0 {
-        return true;
-    }
-
-    let full_bytes = nibbles / 2;
-    let has_half_byte = nibbles % 2 == 1;
-
-    // Check full bytes
-    for i in 0..full_bytes {
-        if a[i] != b[i] {
-            return false;
-        }
-    }
-
-    // Check the half byte (single nibble) if needed
-    if has_half_byte && full_bytes < 32 {
-        let mask = 0xF0; // Check only the high nibble
-        if (a[full_bytes] & mask) != (b[full_bytes] & mask) {
-            return false;
-        }
-    }
-
-    true
-}
-
-pub fn print_results(branch: &[StorageSlot], elapsed_seconds: f64) {
     info!("");
     info!("╔════════════════════════════════════════════════════════════════════════╗");
     info!("║                          MINING RESULTS                                ║");
     info!("╚════════════════════════════════════════════════════════════════════════╝");
     info!("");
     info!("Total depth achieved: {}", branch.len());
+    if let Some(anchor) = anchor {
+        info!(
+            "Extends existing path: 0x{} ({} shared nibbles)",
+            hex::encode(anchor.key),
+            anchor.shared_nibbles
+        );
+    }
+    if let Some(report) = verify_trie(branch, anchor) {
+        info!(
+            "Verified trie depth: {} nodes ({} branch nodes) to the deepest key",
+            report.depth, report.branch_nodes
+        );
+        info!("Node path: {}", format_node_path(&report));
+        if let Some(proof) = &report.proof {
+            info!("Storage proof: {}", proof.describe());
+        }
+        info!("Storage root: {}", report.root);
+    }
     info!("Total time taken: {elapsed_seconds:.2} seconds");
-    info!("ERC20 balance mapping slot: {ERC20_BALANCES_SLOT}");
+    info!("Storage layout: {}", layout.describe());
     info!("");
     info!("═══ Branch Structure (Sequential Addresses) ═══");
     info!("");
 
     // Show the common prefix that all addresses share
-    if branch.len() > 1 {
-        let common_nibbles = branch.len() - 1;
-        let common_prefix = get_common_prefix(branch);
+    let base_nibbles = anchor.map_or(0, SnapshotAnchor::required_nibbles);
+    if branch.len() > 1 || anchor.is_some() {
+        let common_nibbles = base_nibbles + branch.len() - 1;
+        let common_prefix = get_common_prefix(branch, common_nibbles);
         info!("Common prefix ({common_nibbles} nibbles): 0x{common_prefix}");
         info!("");
     }
@@ -316,11 +553,15 @@ pub fn print_results(branch: &[StorageSlot], elapsed_seconds: f64) {
         info!("Level {} (Depth {}):", i + 1, slot.depth);
         info!("  Address:     0x{}", hex::encode(slot.address));
         info!("  Storage Key: 0x{}", hex::encode(slot.storage_key));
+        info!("  Trie Key:    0x{}", hex::encode(slot.trie_key()));
 
         if i > 0 {
             // Show how many nibbles this shares with the previous level
-            let shared = count_shared_nibbles(&branch[i - 1].storage_key, &slot.storage_key);
+            let shared = count_shared_nibbles(&branch[i - 1].trie_key(), &slot.trie_key());
             info!("  Shares {shared} nibbles with previous level");
+        } else if let Some(anchor) = anchor {
+            let shared = count_shared_nibbles(&anchor.key, &slot.trie_key());
+            info!("  Shares {shared} nibbles with the snapshot anchor");
         }
         info!("");
     }
@@ -340,48 +581,103 @@ pub fn print_results(branch: &[StorageSlot], elapsed_seconds: f64) {
     info!("");
 }
 
-/// Get the common prefix shared by all addresses in the branch
-fn get_common_prefix(branch: &[StorageSlot]) -> String {
+/// Render a trie path as `branch -> branch -> leaf`
+pub fn format_node_path(report: &TrieReport) -> String {
+    report
+        .node_types
+        .iter()
+        .map(|kind| format!("{kind:?}").to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Get the common trie key prefix shared by all addresses in the branch
+fn get_common_prefix(branch: &[StorageSlot], min_shared: usize) -> String {
     if branch.is_empty() {
         return String::new();
     }
 
-    let first_key = &branch[0].storage_key;
-    let min_shared = branch.len() - 1;
+    let first_key = branch[0].trie_key();
 
     // Convert to hex and take the appropriate number of nibbles
     let hex_str = hex::encode(first_key);
     hex_str.chars().take(min_shared).collect()
 }
 
-/// Count how many nibbles two storage keys share
-fn count_shared_nibbles(a: &[u8; 32], b: &[u8; 32]) -> usize {
-    let hex_a = hex::encode(a);
-    let hex_b = hex::encode(b);
-
-    hex_a
-        .chars()
-        .zip(hex_b.chars())
-        .take_while(|(ca, cb)| ca == cb)
-        .count()
+/// Write the mined branch to a JSON file
+pub fn write_results(
+    branch: &[StorageSlot],
+    elapsed_seconds: f64,
+    layout: &SlotLayout,
+    anchor: Option<&SnapshotAnchor>,
+    exact: bool,
+    output_path: &str,
+) {
+    let result = StorageMiningResult::from_branch(branch, elapsed_seconds, layout, anchor, exact);
+
+    match serde_json::to_string_pretty(&result) {
+        Ok(json) => {
+            if let Err(e) = fs::write(output_path, json) {
+                log::error!("Failed to write JSON: {e}");
+            } else {
+                info!("Results saved to: {output_path}");
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to serialize to JSON: {e}");
+        }
+    }
 }
 
-/// Generate and compile the Solidity contract with hardcoded storage keys
-pub fn generate_contract(branch: &[StorageSlot]) {
+/// Generate the Solidity contract that writes every mined storage slot
+pub fn generate_contract(
+    branch: &[StorageSlot],
+    layout: &SlotLayout,
+    mode: ContractMode,
+    contract_path: &str,
+) {
     info!("");
     info!("╔════════════════════════════════════════════════════════════════════════╗");
     info!("║                     CONTRACT GENERATION & COMPILATION                  ║");
     info!("╚════════════════════════════════════════════════════════════════════════╝");
     info!("");
 
+    if mode == ContractMode::BalanceOf && !layout.is_plain_address_mapping(ERC20_BALANCES_SLOT) {
+        log::error!(
+            "balanceOf[addr] only reaches slot {ERC20_BALANCES_SLOT} mapping keys, not {}; use the sstore contract mode",
+            layout.describe()
+        );
+        return;
+    }
+
+    // The slots written must be the ones that were mined, not just look like them
+    for (i, slot) in branch.iter().enumerate() {
+        if layout.storage_key(&slot.address) != slot.storage_key {
+            log::error!(
+                "Level {}: storage key does not match key 0x{} in {}, refusing to generate contract",
+                i + 1,
+                hex::encode(slot.address),
+                layout.describe()
+            );
+            return;
+        }
+    }
+
     // Step 1: Generate the contract using Askama template
-    let addresses: Vec<String> = branch
+    let storage_keys: Vec<String> = branch
         .iter()
-        .map(|slot| hex::encode(slot.address))
+        .map(|slot| hex::encode(slot.storage_key))
         .collect();
 
     let template = ContractTemplate {
-        addresses: addresses.clone(),
+        use_balance_of: mode == ContractMode::BalanceOf,
+        accounts: branch
+            .iter()
+            .map(|slot| checksum_address(&slot.address))
+            .collect(),
+        deepest_key: storage_keys.last().cloned().unwrap_or_default(),
+        storage_keys,
+        layout: layout.describe(),
     };
 
     let contract_source = match template.render() {
@@ -392,17 +688,226 @@ pub fn generate_contract(branch: &[StorageSlot]) {
         }
     };
 
-    // Ensure contracts directory exists
-    if let Err(e) = fs::create_dir_all("contracts") {
+    // Step 2: Check the rendered source writes exactly the mined slots
+    if let Err(e) = verify_contract_slots(&contract_source, branch, mode) {
+        log::error!("Generated contract does not write the mined slots: {e}");
+        return;
+    }
+    info!(
+        "Contract writes all {} mined storage slots ({:?} mode)",
+        branch.len(),
+        mode
+    );
+
+    // Ensure the output directory exists
+    if let Some(dir) = std::path::Path::new(contract_path).parent()
+        && let Err(e) = fs::create_dir_all(dir)
+    {
         log::error!("Failed to create contracts directory: {e}");
         return;
     }
 
     // Save the generated contract
-    let contract_path = "contracts/WorstCaseERC20.sol";
     if let Err(e) = fs::write(contract_path, &contract_source) {
         log::error!("Failed to write contract: {e}");
         return;
     }
     info!("Generated contract saved to: {contract_path}");
 }
+
+/// Extract the storage slots a generated contract's constructor writes and compare
+/// them with the mined branch
+fn verify_contract_slots(
+    source: &str,
+    branch: &[StorageSlot],
+    mode: ContractMode,
+) -> Result<(), String> {
+    let written: Vec<[u8; 32]> = match mode {
+        ContractMode::BalanceOf => extract_literals(source, "balanceOf[", "] = 1;")
+            .iter()
+            .map(|literal| {
+                let address: [u8; 20] = crate::parse_hex_array(literal)?;
+                Ok(calculate_storage_slot(&address, ERC20_BALANCES_SLOT))
+            })
+            .collect::<Result<_, String>>()?,
+        ContractMode::Sstore => extract_literals(source, "sstore(", ", 1)")
+            .iter()
+            .map(|literal| crate::parse_hex_array(literal))
+            .collect::<Result<_, String>>()?,
+    };
+
+    let mined: Vec<[u8; 32]> = branch.iter().map(|slot| slot.storage_key).collect();
+    if written != mined {
+        return Err(format!(
+            "constructor writes {} slots, {} of which differ from the {} mined slots",
+            written.len(),
+            written.iter().filter(|key| !mined.contains(key)).count(),
+            mined.len()
+        ));
+    }
+
+    let deepest = format!(
+        "sstore(0x{}, value)",
+        hex::encode(mined.last().unwrap_or(&[0; 32]))
+    );
+    if !source.contains(&deepest) {
+        return Err("attack() does not write the deepest mined slot".to_string());
+    }
+
+    Ok(())
+}
+
+/// Collect every literal found between `open` and `close` in the source
+fn extract_literals<'a>(source: &'a str, open: &str, close: &str) -> Vec<&'a str> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let start = line.find(open)? + open.len();
+            let end = start + line[start..].find(close)?;
+            Some(line[start..end].trim())
+        })
+        .collect()
+}
+
+/// Format an address with its EIP-55 mixed-case checksum, as Solidity requires for literals
+pub fn checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+
+    let mut hasher = Keccak::v256();
+    let mut hash = [0u8; 32];
+    hasher.update(lower.as_bytes());
+    hasher.finalize(&mut hash);
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0F;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{checksummed}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branch_of(addresses: &[[u8; 20]]) -> Vec<StorageSlot> {
+        addresses
+            .iter()
+            .enumerate()
+            .map(|(depth, address)| StorageSlot {
+                address: *address,
+                storage_key: calculate_storage_slot(address, ERC20_BALANCES_SLOT),
+                depth,
+                time_taken: 0.0,
+            })
+            .collect()
+    }
+
+    /// EIP-55 reference vectors
+    #[test]
+    fn test_checksum_address() {
+        for expected in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        ] {
+            let address: [u8; 20] = crate::parse_hex_array(expected).unwrap();
+            assert_eq!(checksum_address(&address), expected);
+        }
+    }
+
+    /// Levels share prefixes of their hashed keys, so the trie clients build from the slots
+    /// splits off one branch node per level
+    #[test]
+    fn test_mined_branch_is_deep_in_hashed_trie() {
+        let layout = SlotLayout::default();
+        let branch = mine_deep_branch(3, 2, false, &layout, None, true, Vec::new(), None);
+        verify_levels(&branch, &layout, None, true).unwrap();
+
+        let report = verify_trie(&branch, None).unwrap();
+        assert_eq!(report.branch_nodes, 2);
+        assert_eq!(report.depth, 4);
+
+        let mut raw = Trie::new();
+        for slot in &branch {
+            raw.insert(&slot.storage_key, rlp::encode_u64(1));
+        }
+        assert_ne!(raw.report(&branch[2].storage_key).root, report.root);
+    }
+
+    /// A checkpoint only resumes the run it came from, and mining continues below its last level
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let layout = SlotLayout::default();
+        let path = std::env::temp_dir()
+            .join(format!("storage_resume_{}.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let branch = mine_deep_branch(2, 2, false, &layout, None, false, Vec::new(), None);
+        let result = StorageMiningResult::from_branch(&branch, 0.0, &layout, None, false);
+        checkpoint::save(&path, &result).unwrap();
+
+        assert!(load_checkpoint(&path, 1, &layout, false).is_err());
+        assert!(load_checkpoint(&path, 3, &layout, true).is_err());
+        let other_layout = LayoutInfo {
+            base_slot: format!("0x{:064x}", 1),
+            ..layout.info()
+        };
+        assert!(load_checkpoint(&path, 3, &other_layout.to_layout().unwrap(), false).is_err());
+
+        let resumed = load_checkpoint(&path, 3, &layout, false)
+            .unwrap()
+            .to_branch()
+            .unwrap();
+        let extended = mine_deep_branch(3, 2, false, &layout, None, false, resumed, None);
+        assert_eq!(extended.len(), 3);
+        assert_eq!(extended[1].storage_key, branch[1].storage_key);
+        verify_levels(&extended, &layout, None, false).unwrap();
+
+        // A damaged level is rejected instead of resumed
+        let mut damaged = result;
+        damaged.accounts[1].storage_slot = damaged.accounts[0].storage_slot.clone();
+        checkpoint::save(&path, &damaged).unwrap();
+        assert!(load_checkpoint(&path, 3, &layout, false).is_err());
+        checkpoint::remove(&path);
+    }
+
+    /// Both contract modes must render exactly the mined storage keys
+    #[test]
+    fn test_rendered_contract_writes_mined_slots() {
+        let branch = branch_of(&[[0x11; 20], [0x22; 20], [0x33; 20]]);
+
+        for mode in [ContractMode::BalanceOf, ContractMode::Sstore] {
+            let storage_keys: Vec<String> = branch
+                .iter()
+                .map(|slot| hex::encode(slot.storage_key))
+                .collect();
+            let source = ContractTemplate {
+                use_balance_of: mode == ContractMode::BalanceOf,
+                accounts: branch
+                    .iter()
+                    .map(|slot| checksum_address(&slot.address))
+                    .collect(),
+                deepest_key: storage_keys.last().cloned().unwrap(),
+                storage_keys,
+                layout: SlotLayout::default().describe(),
+            }
+            .render()
+            .unwrap();
+
+            verify_contract_slots(&source, &branch, mode).unwrap();
+            if mode == ContractMode::Sstore {
+                assert!(source.contains("solidity mapping(address) at slot 0x0"));
+            }
+            assert!(verify_contract_slots(&source, &branch[..2], mode).is_err());
+        }
+    }
+}
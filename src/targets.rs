@@ -0,0 +1,158 @@
+//! # Targets Module
+//!
+//! Index of the outstanding auxiliary targets of many contracts, where each target is a
+//! contract hash plus the depth an auxiliary has to reach below it. Targets are bucketed by
+//! their prefix at each depth, so a candidate hash is checked against all of them with one
+//! table lookup per distinct depth instead of one comparison per target. Batch mining draws
+//! one stream of candidates for every contract at once, and a candidate that misses its own
+//! contract can still fill another one's target.
+//!
+//! ## Key Functions
+//! - `TargetIndex::new`: Indexes the targets of a set of contract hashes
+//! - `TargetIndex::matches_any`: Whether a hash fills any unfilled target (the search predicate)
+//! - `TargetIndex::claim`: Assigns a hash to the deepest unfilled target it fills
+
+use crate::search::prefix_matches;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Nibbles of a hash that go into its bucket key
+const KEY_NIBBLES: usize = 16;
+
+/// A contract hash and the depth one of its auxiliaries has to share with it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub contract: usize,
+    pub depth: usize,
+}
+
+/// Prefix index over the unfilled targets of a batch of contracts
+pub struct TargetIndex {
+    contract_hashes: Vec<[u8; 32]>,
+    targets: Vec<Target>,
+    filled: Vec<AtomicBool>,
+    /// Per depth (deepest first): targets bucketed by the hash prefix they require
+    buckets: Vec<(usize, HashMap<u64, Vec<usize>>)>,
+    exact: bool,
+}
+
+impl TargetIndex {
+    /// Index one target per contract and depth in `first_depth..=target_depth`
+    pub fn new(
+        contract_hashes: Vec<[u8; 32]>,
+        first_depth: usize,
+        target_depth: usize,
+        exact: bool,
+    ) -> Self {
+        let mut targets = Vec::new();
+        let mut buckets = Vec::new();
+        for depth in (first_depth..=target_depth).rev() {
+            let mut by_prefix: HashMap<u64, Vec<usize>> = HashMap::new();
+            for (contract, hash) in contract_hashes.iter().enumerate() {
+                by_prefix
+                    .entry(bucket_key(hash, depth))
+                    .or_default()
+                    .push(targets.len());
+                targets.push(Target { contract, depth });
+            }
+            buckets.push((depth, by_prefix));
+        }
+
+        TargetIndex {
+            contract_hashes,
+            filled: targets.iter().map(|_| AtomicBool::new(false)).collect(),
+            targets,
+            buckets,
+            exact,
+        }
+    }
+
+    /// Unfilled targets `hash` fills, deepest first
+    fn matching<'a>(&'a self, hash: &'a [u8; 32]) -> impl Iterator<Item = usize> + 'a {
+        self.buckets
+            .iter()
+            .filter_map(|(depth, by_prefix)| by_prefix.get(&bucket_key(hash, *depth)))
+            .flatten()
+            .copied()
+            .filter(|&id| {
+                let target = self.targets[id];
+                !self.filled[id].load(Ordering::Relaxed)
+                    && prefix_matches(
+                        hash,
+                        &self.contract_hashes[target.contract],
+                        target.depth,
+                        self.exact,
+                    )
+            })
+    }
+
+    /// Whether `hash` fills any unfilled target
+    pub fn matches_any(&self, hash: &[u8; 32]) -> bool {
+        self.matching(hash).next().is_some()
+    }
+
+    /// Mark the deepest unfilled target `hash` fills as filled and return it
+    pub fn claim(&self, hash: &[u8; 32]) -> Option<Target> {
+        let id = self.matching(hash).next()?;
+        self.filled[id].store(true, Ordering::Relaxed);
+        Some(self.targets[id])
+    }
+
+    /// Number of targets not filled yet
+    pub fn remaining(&self) -> usize {
+        self.filled
+            .iter()
+            .filter(|filled| !filled.load(Ordering::Relaxed))
+            .count()
+    }
+}
+
+/// The first `depth` nibbles (at most 16) of a hash, with the rest masked off
+fn bucket_key(hash: &[u8; 32], depth: usize) -> u64 {
+    let nibbles = depth.min(KEY_NIBBLES);
+    let prefix = u64::from_be_bytes(hash[..8].try_into().unwrap());
+    match nibbles {
+        0 => 0,
+        _ => prefix & (u64::MAX << (64 - nibbles * 4)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claims_deepest_target_across_contracts() {
+        let mut other = [0x11; 32];
+        other[0] = 0x12;
+        let index = TargetIndex::new(vec![[0x11; 32], other], 1, 3, false);
+        assert_eq!(index.remaining(), 6);
+
+        // Shares 3 nibbles with the first contract and 1 with the other
+        let mut hash = [0x11; 32];
+        hash[1] = 0x1f;
+        assert_eq!(
+            index.claim(&hash),
+            Some(Target {
+                contract: 0,
+                depth: 3
+            })
+        );
+        assert_eq!(
+            index.claim(&hash),
+            Some(Target {
+                contract: 0,
+                depth: 2
+            })
+        );
+        assert_eq!(index.claim(&hash).map(|target| target.depth), Some(1));
+        assert_eq!(index.claim(&hash).map(|target| target.depth), Some(1));
+        assert_eq!(index.claim(&hash), None);
+        assert_eq!(index.remaining(), 2);
+
+        // In exact mode a hash only fills the depth it shares exactly
+        let exact = TargetIndex::new(vec![[0x11; 32]], 1, 3, true);
+        assert_eq!(exact.claim(&hash).map(|target| target.depth), Some(3));
+        assert_eq!(exact.claim(&hash), None);
+    }
+}